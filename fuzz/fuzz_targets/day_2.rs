@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use advent_of_code_2023::day_2::{self, CubeCounts};
+use advent_of_code_2023::explain::Trace;
+use advent_of_code_2023_fuzz::budget;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = day_2::solve(data, &CubeCounts::elf_bag(), &mut Trace::disabled(), &budget());
+});
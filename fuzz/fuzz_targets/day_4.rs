@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use advent_of_code_2023::day_4;
+use advent_of_code_2023::explain::Trace;
+use advent_of_code_2023_fuzz::budget;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = day_4::solve(data, &mut Trace::disabled(), &budget());
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use advent_of_code_2023::day_5;
+use advent_of_code_2023::explain::Trace;
+use advent_of_code_2023_fuzz::budget;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = day_5::solve(input, &mut Trace::disabled(), &budget());
+        let _ = day_5::to_svg(input);
+    }
+});
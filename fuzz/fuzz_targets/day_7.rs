@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use advent_of_code_2023::day_7;
+use advent_of_code_2023::explain::Trace;
+use advent_of_code_2023_fuzz::budget;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = day_7::solve(data, &mut Trace::disabled(), &budget());
+    let _ = day_7::display(data, &mut std::io::sink());
+});
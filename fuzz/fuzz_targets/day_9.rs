@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use advent_of_code_2023::day_9;
+use advent_of_code_2023::explain::Trace;
+use advent_of_code_2023_fuzz::budget;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = day_9::solve(data, &mut Trace::disabled(), &budget());
+    let _ = day_9::solve_exact(data, &mut Trace::disabled(), &budget());
+    let _ = day_9::display(data, &mut std::io::sink());
+});
@@ -0,0 +1,11 @@
+use advent_of_code_2023::budget::Budget;
+
+/// How many steps a target may take on one input. Walks and loops that never finish then
+/// give up with `Interrupted`, the path the solvers were hardened for, instead of running
+/// into libFuzzer's timeout and being reported as hangs.
+const STEP_LIMIT: u64 = 100_000;
+
+/// The budget every target solves with.
+pub fn budget() -> Budget {
+    Budget::unlimited().with_step_limit(STEP_LIMIT)
+}
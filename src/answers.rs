@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::helper::invalid_data;
+
+/// What a day printed for each part, `None` for a part it could not answer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Answers {
+    pub part_one: Option<String>,
+    pub part_two: Option<String>,
+}
+
+impl Answers {
+    pub fn new(part_one: impl Display, part_two: impl Display) -> Self {
+        Answers { part_one: Some(part_one.to_string()), part_two: Some(part_two.to_string()) }
+    }
+
+    /// Part `part` of the two, counting from 1.
+    pub fn part(&self, part: usize) -> Option<&str> {
+        match part {
+            1 => self.part_one.as_deref(),
+            2 => self.part_two.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// How an answer compares with the one known to be right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Correct,
+    /// A different answer, or none at all.
+    Wrong,
+    /// The right answer is not known.
+    Unknown,
+}
+
+impl Status {
+    pub fn of(answer: Option<&str>, expected: Option<&str>) -> Self {
+        match (answer, expected) {
+            (_, None) => Status::Unknown,
+            (Some(answer), Some(expected)) if answer == expected => Status::Correct,
+            (_, Some(_)) => Status::Wrong,
+        }
+    }
+}
+
+/// The known right answers, keyed by the path of the input they are for, as written
+/// in `data/answers.json`. A missing file knows no answers.
+pub fn load_expected(path: &Path) -> io::Result<BTreeMap<String, Answers>> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|err| invalid_data(format!("{}: {}", path.display(), err))),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        let answers = Answers::new(288, 71503);
+        let expected = Answers { part_one: Some(String::from("288")), part_two: None };
+        assert_eq!(Status::of(answers.part(1), expected.part(1)), Status::Correct);
+        assert_eq!(Status::of(answers.part(2), expected.part(2)), Status::Unknown);
+        assert_eq!(Status::of(Some("1"), Some("2")), Status::Wrong);
+        assert_eq!(Status::of(None, Some("2")), Status::Wrong);
+        assert_eq!(answers.part(3), None);
+    }
+
+    #[test]
+    fn test_stored_answers() {
+        let expected = load_expected(Path::new("data/answers.json")).unwrap();
+        assert_eq!(expected["data/examples/day_6/example.txt"], Answers::new(288, 71503));
+        assert!(load_expected(Path::new("data/missing.json")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+use std::cell::Cell;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// How many steps go by between looks at the clock.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Why a solver stopped before reaching its answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupted {
+    TimedOut,
+    OutOfSteps { limit: u64 },
+}
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interrupted::TimedOut => write!(f, "timed out"),
+            Interrupted::OutOfSteps { limit } => write!(f, "gave up after {} steps", limit),
+        }
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+impl From<Interrupted> for io::Error {
+    fn from(interrupted: Interrupted) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, interrupted)
+    }
+}
+
+/// How much work a solver may do before giving up.
+///
+/// Solvers call `step` from their loops. The step limit is exact, the deadline is only
+/// looked at every `CHECK_INTERVAL` steps so the check stays cheap.
+#[derive(Debug, Default)]
+pub struct Budget {
+    deadline: Option<Instant>,
+    step_limit: Option<u64>,
+    steps: Cell<u64>,
+}
+
+impl Budget {
+    pub fn unlimited() -> Self {
+        Budget::default()
+    }
+
+    /// Gives up `timeout` from now. A timeout too long to represent never expires.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Instant::now().checked_add(timeout);
+        self
+    }
+
+    pub fn with_step_limit(mut self, limit: u64) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps.get()
+    }
+
+    /// Counts one step of work, failing once the budget is spent.
+    pub fn step(&self) -> Result<(), Interrupted> {
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+        if let Some(limit) = self.step_limit {
+            if steps > limit {
+                return Err(Interrupted::OutOfSteps { limit });
+            }
+        }
+        if steps % CHECK_INTERVAL == 1 && self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(Interrupted::TimedOut);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_limit() {
+        let budget = Budget::unlimited().with_step_limit(3);
+        assert!((0..3).all(|_| budget.step().is_ok()));
+        assert_eq!(budget.step(), Err(Interrupted::OutOfSteps { limit: 3 }));
+        assert_eq!(budget.steps(), 4);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let budget = Budget::unlimited().with_timeout(Duration::ZERO);
+        assert_eq!(budget.step(), Err(Interrupted::TimedOut));
+        assert_eq!(io::Error::from(Interrupted::TimedOut).kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let budget = Budget::unlimited().with_timeout(Duration::MAX);
+        assert!((0..10 * CHECK_INTERVAL).all(|_| budget.step().is_ok()));
+    }
+}
@@ -1,63 +1,236 @@
-use std::cmp::{max, min};
-use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead};
 
-pub fn solve(input: &str) {
-    println!("Solving day 1 problems...");
-    let strings = input.lines();
-    let mut part_one_answer: u32 = 0;
-    let mut part_two_answer: u32 = 0;
-    for word in strings {
-        part_one_answer += extract_numeric_digits(word);
-        part_two_answer += parse_number_from_word(word);
-    }
-    println!("The answer is to the first gold star for day 1 is: {}", part_one_answer);
-    println!("The answer is to the second gold star for day 1 is: {}", part_two_answer);
+use crate::answers::Answers;
+use crate::budget::Budget;
+use crate::explain::Trace;
+
+pub const ENGLISH: [&str; 10] = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+pub const GERMAN: [&str; 10] = ["null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun"];
+
+/// The words a [`DigitScanner`] recognises, each mapped to the digit it stands for.
+#[derive(Debug, Clone, Default)]
+pub struct DigitDictionary {
+    entries: Vec<(String, u32)>,
+}
+
+impl DigitDictionary {
+    /// The plain digits `1` to `9`, plus `0` when `include_zero` is set.
+    pub fn digits(include_zero: bool) -> Self {
+        let first = if include_zero { 0 } else { 1 };
+        let mut dictionary = DigitDictionary::default();
+        for value in first..=9 {
+            dictionary = dictionary.with_word(&value.to_string(), value);
+        }
+        dictionary
+    }
+
+    /// Adds the spelled out digits of a language, `words[n]` being the word for `n`.
+    pub fn with_language(mut self, words: &[&str; 10], include_zero: bool) -> Self {
+        let first = if include_zero { 0 } else { 1 };
+        for (value, word) in words.iter().enumerate().skip(first) {
+            self = self.with_word(word, value as u32);
+        }
+        self
+    }
+
+    pub fn with_word(mut self, word: &str, value: u32) -> Self {
+        if !word.is_empty() {
+            self.entries.push((String::from(word), value));
+        }
+        self
+    }
+}
+
+/// A digit found in a line, `start..end` being its byte range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigitMatch {
+    pub start: usize,
+    pub end: usize,
+    pub value: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoDigitsError {
+    pub line: String,
 }
 
+impl fmt::Display for NoDigitsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no digits found in \"{}\"", self.line)
+    }
+}
+
+impl std::error::Error for NoDigitsError {}
 
-fn extract_numeric_digits(word: &str) -> u32 {
-    let chars: Vec<_> = word.chars()
-        .filter(|x| x.is_digit(10))
-        .map(|x| x.to_digit(10).unwrap())
-        .collect();
-    chars[0] * 10 + chars[chars.len() - 1]
+/// Aho-Corasick automaton over the bytes of a [`DigitDictionary`], finding every
+/// occurrence of every word in one pass, overlapping ones like "eightwo" included.
+#[derive(Debug, Clone)]
+pub struct DigitScanner {
+    // Goto function completed with the failure links, so every byte is a single lookup.
+    transitions: Vec<[usize; 256]>,
+    // Words ending in each state, including those reached through failure links, as (length, value).
+    outputs: Vec<Vec<(usize, u32)>>,
 }
 
+impl DigitScanner {
+    pub fn new(dictionary: &DigitDictionary) -> Self {
+        let mut transitions = vec![[0; 256]];
+        let mut outputs = vec![Vec::new()];
+
+        for (word, value) in &dictionary.entries {
+            let mut state = 0;
+            for &byte in word.as_bytes() {
+                if transitions[state][byte as usize] == 0 {
+                    transitions.push([0; 256]);
+                    outputs.push(Vec::new());
+                    transitions[state][byte as usize] = transitions.len() - 1;
+                }
+                state = transitions[state][byte as usize];
+            }
+            outputs[state].push((word.len(), *value));
+        }
+
+        // Breadth first, so a state's failure target is always complete before the state itself.
+        // Trie edges never point back at the root, which is what tells them apart from gaps.
+        let mut fail = vec![0; transitions.len()];
+        let mut queue: VecDeque<usize> = transitions[0].iter().copied().filter(|&next| next != 0).collect();
+        while let Some(state) = queue.pop_front() {
+            let fallback_row = transitions[fail[state]];
+            for (next, fallback) in transitions[state].iter_mut().zip(fallback_row) {
+                if *next == 0 {
+                    *next = fallback;
+                } else {
+                    fail[*next] = fallback;
+                    let inherited = outputs[fallback].clone();
+                    outputs[*next].extend(inherited);
+                    queue.push_back(*next);
+                }
+            }
+        }
+
+        DigitScanner { transitions, outputs }
+    }
 
-fn parse_number_from_word(word: &str) -> u32 {
-    let pattern_vec = vec!["one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
-    let mut number_to_idx_mapping = HashMap::new();
-    let mut min_index = word.len();
-    let mut max_index = 0usize;
+    /// Every digit in `line`, ordered by where it ends.
+    pub fn scan<'a>(&'a self, line: &'a str) -> impl Iterator<Item=DigitMatch> + 'a {
+        line.bytes()
+            .enumerate()
+            .scan(0, move |state, (idx, byte)| {
+                *state = self.transitions[*state][byte as usize];
+                Some((idx + 1, *state))
+            })
+            .flat_map(move |(end, state)| {
+                self.outputs[state].iter().map(move |&(len, value)| DigitMatch { start: end - len, end, value })
+            })
+    }
 
-    for pattern in pattern_vec {
-        let indices: Vec<_> = word.match_indices(pattern).collect();
-        if !indices.is_empty() {
-            let curr_min_index = *indices.iter().min().unwrap();
-            let curr_max_index = *indices.iter().max().unwrap();
-            number_to_idx_mapping.insert(curr_max_index.0, pattern);
-            number_to_idx_mapping.insert(curr_min_index.0, pattern);
-            min_index = min(min_index, curr_min_index.0);
-            max_index = max(max_index, curr_max_index.0);
+    /// The first digit followed by the last one, read as a two digit number.
+    pub fn calibration_value(&self, line: &str) -> Result<u32, NoDigitsError> {
+        let mut first: Option<DigitMatch> = None;
+        let mut last: Option<DigitMatch> = None;
+        for digit in self.scan(line) {
+            if first.is_none_or(|first| digit.start < first.start) {
+                first = Some(digit);
+            }
+            if last.is_none_or(|last| digit.start > last.start) {
+                last = Some(digit);
+            }
+        }
+        match (first, last) {
+            (Some(first), Some(last)) => Ok(first.value * 10 + last.value),
+            _ => Err(NoDigitsError { line: String::from(line) }),
         }
     }
+}
 
-    let first_digit = get_int_from_string_mapping(number_to_idx_mapping.get(&min_index).unwrap());
-    let second_digit = get_int_from_string_mapping(number_to_idx_mapping.get(&max_index).unwrap());
-    first_digit * 10 + second_digit
+pub fn solve<R: BufRead>(input: R, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
+    println!("Solving day 1 problems...");
+    let digit_scanner = DigitScanner::new(&DigitDictionary::digits(false));
+    let word_scanner = DigitScanner::new(&DigitDictionary::digits(false).with_language(&ENGLISH, false));
+    // Part two examples have lines spelling every digit, those leave part one without an answer.
+    let mut part_one_answer: Result<u64, io::Error> = Ok(0);
+    let mut part_two_answer: u64 = 0;
+    for (line_idx, word) in input.lines().enumerate() {
+        budget.step()?;
+        let word = word?;
+        let word = word.trim();
+        if word.is_empty() {
+            continue;
+        }
+        let invalid_line = |err: NoDigitsError| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_idx + 1, err));
+        let words_value = word_scanner.calibration_value(word).map_err(invalid_line)?;
+        match digit_scanner.calibration_value(word) {
+            Ok(digits_value) => {
+                trace.note(|| format!("line {} \"{}\": {} from digits, {} with words", line_idx + 1, word, digits_value, words_value));
+                if let Ok(answer) = &mut part_one_answer {
+                    *answer += u64::from(digits_value);
+                }
+            }
+            Err(err) => {
+                trace.note(|| format!("line {} \"{}\": no digits, {} with words", line_idx + 1, word, words_value));
+                if part_one_answer.is_ok() {
+                    part_one_answer = Err(invalid_line(err));
+                }
+            }
+        }
+        part_two_answer += u64::from(words_value);
+    }
+    let part_one_answer = match part_one_answer {
+        Ok(answer) => {
+            println!("The answer is to the first gold star for day 1 is: {}", answer);
+            Some(answer.to_string())
+        }
+        Err(err) => {
+            println!("The first gold star for day 1 has no answer, {}", err);
+            None
+        }
+    };
+    println!("The answer is to the second gold star for day 1 is: {}", part_two_answer);
+    Ok(Answers { part_one: part_one_answer, part_two: Some(part_two_answer.to_string()) })
 }
 
-fn get_int_from_string_mapping(str_num: &str) -> u32 {
-    match str_num {
-        "one" | "1" => 1,
-        "two" | "2" => 2,
-        "three" | "3" => 3,
-        "four" | "4" => 4,
-        "five" | "5" => 5,
-        "six" | "6" => 6,
-        "seven" | "7" => 7,
-        "eight" | "8" => 8,
-        "nine" | "9" => 9,
-        _ => unreachable!()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let scanner = DigitScanner::new(&DigitDictionary::digits(false).with_language(&ENGLISH, false));
+        let lines = ["two1nine", "eightwothree", "abcone2threexyz", "xtwone3four", "4nineeightseven2", "zoneight234", "7pqrstsixteen"];
+        let total: u32 = lines.iter().map(|line| scanner.calibration_value(line).unwrap()).sum();
+        assert_eq!(total, 281);
+    }
+
+    #[test]
+    fn test_solve_without_plain_digits() {
+        let input = "two1nine\neightwothree\nabcone2threexyz\n";
+        let answers = solve(input.as_bytes(), &mut Trace::disabled(), &Budget::unlimited()).unwrap();
+        assert_eq!(answers, Answers { part_one: None, part_two: Some(String::from("125")) });
+    }
+
+    #[test]
+    fn test_overlapping_words() {
+        let scanner = DigitScanner::new(&DigitDictionary::digits(false).with_language(&ENGLISH, false));
+        let values: Vec<_> = scanner.scan("eightwone").map(|digit| digit.value).collect();
+        assert_eq!(values, vec![8, 2, 1]);
+        assert_eq!(scanner.calibration_value("eightwo"), Ok(82));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_zero_and_other_languages() {
+        let without_zero = DigitScanner::new(&DigitDictionary::digits(false).with_language(&GERMAN, false));
+        assert_eq!(without_zero.calibration_value("nullfünfx0"), Ok(55));
+
+        let with_zero = DigitScanner::new(&DigitDictionary::digits(true).with_language(&GERMAN, true));
+        assert_eq!(with_zero.calibration_value("nullfünfx0"), Ok(0));
+        assert_eq!(with_zero.calibration_value("achtnull"), Ok(80));
+    }
+
+    #[test]
+    fn test_line_without_digits() {
+        let scanner = DigitScanner::new(&DigitDictionary::digits(false));
+        assert_eq!(scanner.calibration_value("onetwo"), Err(NoDigitsError { line: String::from("onetwo") }));
+    }
+}
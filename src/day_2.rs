@@ -1,104 +1,377 @@
-use std::cmp::max;
-use crate::helper as helper;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use std::sync::OnceLock;
 
-#[derive(Debug)]
-struct SubGame {
-    green: u32,
-    blue: u32,
-    red: u32,
+use crate::answers::Answers;
+use crate::budget::Budget;
+use crate::explain::Trace;
+use crate::helper::invalid_data;
+
+/// Cube counts keyed by colour, used both for a single draw and for a bag.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CubeCounts {
+    counts: BTreeMap<String, u32>,
 }
 
-impl SubGame {
-    fn product(&self) -> u32 {
-        return self.blue * self.green * self.red;
+impl CubeCounts {
+    /// The bag from the puzzle: 12 red, 13 green and 14 blue cubes.
+    pub fn elf_bag() -> Self {
+        CubeCounts::default()
+            .with("red", 12)
+            .with("green", 13)
+            .with("blue", 14)
+    }
+
+    pub fn with(mut self, colour: &str, count: u32) -> Self {
+        self.counts.insert(String::from(colour), count);
+        self
+    }
+
+    pub fn get(&self, colour: &str) -> u32 {
+        self.counts.get(colour).copied().unwrap_or(0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(&str, u32)> {
+        self.counts.iter().map(|(colour, &count)| (colour.as_str(), count))
+    }
+
+    /// The product of every colour's count, the "power" of a set of cubes.
+    /// `None` when it does not fit in a `u64`.
+    pub fn product(&self) -> Option<u64> {
+        self.counts.values().try_fold(1u64, |product, &count| product.checked_mul(u64::from(count)))
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.values().copied().map(u64::from).sum()
+    }
+
+    fn raise_to(&mut self, other: &CubeCounts) {
+        for (colour, count) in other.iter() {
+            let entry = self.counts.entry(String::from(colour)).or_insert(0);
+            *entry = (*entry).max(count);
+        }
     }
 }
 
-#[derive(Debug)]
-struct GameInfo {
-    id: u32,
-    sub_games: Vec<SubGame>,
+impl fmt::Display for CubeCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<_> = self.iter().map(|(colour, count)| format!("{} {}", count, colour)).collect();
+        write!(f, "{}", parts.join(", "))
+    }
 }
 
-pub fn solve(input: &str) {
-    println!("Solving day 2 problems...");
-    let lines = input.lines();
-    let mut p_1_answer = 0;
-    let max_poss_sub_game = SubGame {
-        red: 12,
-        green: 13,
-        blue: 14,
-    };
-    let mut p_2_answer = 0;
-
-    for line in lines {
-        let game_info = parse_game_info(line);
-        if is_game_possible(&game_info, &max_poss_sub_game) {
-            p_1_answer += game_info.id;
+/// Parses the puzzle's own draw syntax, e.g. `3 blue, 4 red`.
+impl FromStr for CubeCounts {
+    type Err = ParseGameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cube_counts = CubeCounts::default();
+        for ball in s.split(',').map(str::trim).filter(|ball| !ball.is_empty()) {
+            let mut parts = ball.split_whitespace();
+            let (Some(count), Some(colour), None) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(ParseGameError(format!("expected \"<count> <colour>\", found \"{}\"", ball)));
+            };
+            let count: u32 = count.parse()
+                .map_err(|_| ParseGameError(format!("invalid cube count \"{}\"", count)))?;
+            let entry = cube_counts.counts.entry(String::from(colour)).or_insert(0);
+            *entry = entry.checked_add(count)
+                .ok_or_else(|| ParseGameError(format!("too many {} cubes in \"{}\"", colour, s.trim())))?;
         }
-        let max_cubes_needed = get_max_cubes_needed_for_valid_game(&game_info);
-        p_2_answer += max_cubes_needed.product();
+        Ok(cube_counts)
     }
+}
 
-    println!("The answer to the first part of day 2 problem is: {}", p_1_answer);
-    println!("The answer to the second part of day 2 problem is: {}", p_2_answer);
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseGameError(String);
+
+impl fmt::Display for ParseGameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
-fn is_game_possible(curr_game: &GameInfo, max_poss_sub_game: &SubGame) -> bool {
-    for sub_game in curr_game.sub_games.as_slice() {
-        if sub_game.red > max_poss_sub_game.red || sub_game.green > max_poss_sub_game.green || sub_game.blue > max_poss_sub_game.blue {
-            return false;
-        }
+impl std::error::Error for ParseGameError {}
+
+/// A draw that took more cubes of `colour` out than the bag holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub draw_idx: usize,
+    pub colour: String,
+    pub drawn: u32,
+    pub limit: u32,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "draw {} took {} {} but the bag only holds {}", self.draw_idx + 1, self.drawn, self.colour, self.limit)
     }
-    true
 }
 
-fn get_max_cubes_needed_for_valid_game(curr_game: &GameInfo) -> SubGame {
-    let mut max_cubes_sub_game: SubGame = SubGame {
-        green: 0,
-        blue: 0,
-        red: 0,
-    };
+#[derive(Debug, Clone)]
+pub struct GameInfo {
+    pub id: u32,
+    pub sub_games: Vec<CubeCounts>,
+}
 
-    for sub_game in curr_game.sub_games.as_slice() {
-        max_cubes_sub_game.red = max(max_cubes_sub_game.red, sub_game.red);
-        max_cubes_sub_game.blue = max(max_cubes_sub_game.blue, sub_game.blue);
-        max_cubes_sub_game.green = max(max_cubes_sub_game.green, sub_game.green);
+impl GameInfo {
+    /// The first draw asking for more cubes of some colour than `bag` holds, if any.
+    pub fn first_violation(&self, bag: &CubeCounts) -> Option<Violation> {
+        for (draw_idx, sub_game) in self.sub_games.iter().enumerate() {
+            for (colour, drawn) in sub_game.iter() {
+                let limit = bag.get(colour);
+                if drawn > limit {
+                    return Some(Violation { draw_idx, colour: String::from(colour), drawn, limit });
+                }
+            }
+        }
+        None
+    }
+
+    pub fn is_possible(&self, bag: &CubeCounts) -> bool {
+        self.first_violation(bag).is_none()
+    }
+
+    /// The smallest bag every draw of this game fits in.
+    pub fn minimum_bag(&self) -> CubeCounts {
+        let mut max_cubes_sub_game = CubeCounts::default();
+        for sub_game in &self.sub_games {
+            max_cubes_sub_game.raise_to(sub_game);
+        }
+        max_cubes_sub_game
     }
 
-    max_cubes_sub_game
+    /// The natural log of the chance of seeing this game's draws from `bag`, if every
+    /// cube is drawn uniformly at random and put back before the next one.
+    /// Negative infinity when a draw holds a colour the bag has none of.
+    pub fn log_likelihood(&self, bag: &CubeCounts) -> f64 {
+        let total = bag.total() as f64;
+        self.sub_games.iter()
+            .map(|sub_game| {
+                let drawn: u64 = sub_game.iter().map(|(_, count)| u64::from(count)).sum();
+                // A colour drawn zero times adds nothing, even one the bag has none of.
+                sub_game.iter().filter(|&(_, count)| count > 0).fold(ln_factorial(drawn), |log_p, (colour, count)| {
+                    let share = if total == 0.0 { 0.0 } else { bag.get(colour) as f64 / total };
+                    log_p - ln_factorial(u64::from(count)) + count as f64 * share.ln()
+                })
+            })
+            .sum()
+    }
 }
 
-fn parse_game_info(line: &str) -> GameInfo {
-    let line_substr: Vec<_> = line.split(":").collect();
-    let game_id: Vec<u32> = helper::filter_digits_from_str(line_substr[0]);
-    let games: Vec<_> = line_substr[1].split(";").collect();
-    let sub_games: Vec<SubGame> = parse_sub_games(games);
-    GameInfo { id: helper::convert_num_arr_to_num(game_id), sub_games }
+/// Up to where `ln_factorial` looks the answer up instead of approximating it.
+const LN_FACTORIAL_TABLE_LEN: usize = 256;
+
+/// The natural log of `n!`, exact from a table for small `n` and from Stirling's series
+/// above it, where the terms left out are below `1e-14`.
+fn ln_factorial(n: u64) -> f64 {
+    static TABLE: OnceLock<Vec<f64>> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        let mut table = vec![0.0; LN_FACTORIAL_TABLE_LEN];
+        for i in 2..LN_FACTORIAL_TABLE_LEN {
+            table[i] = table[i - 1] + (i as f64).ln();
+        }
+        table
+    });
+    if let Some(&ln) = table.get(n as usize) {
+        return ln;
+    }
+    let n = n as f64;
+    n * n.ln() - n + 0.5 * (2.0 * std::f64::consts::PI * n).ln() + 1.0 / (12.0 * n) - 1.0 / (360.0 * n.powi(3))
 }
 
-fn parse_sub_games(games: Vec<&str>) -> Vec<SubGame> {
-    let mut sub_games: Vec<SubGame> = Vec::new();
+/// The smallest single bag that makes every game possible.
+pub fn minimum_bag_for_all(games: &[GameInfo]) -> CubeCounts {
+    let mut bag = CubeCounts::default();
     for game in games {
-        let balls_info: Vec<_> = game.split(",").collect();
-        let mut sub_game: SubGame = SubGame {
-            green: 0,
-            blue: 0,
-            red: 0,
-        };
-        for ball in balls_info {
-            let count_of_ball: Vec<u32> = helper::filter_digits_from_str(ball);
-            let color_of_ball: String = ball.chars()
-                .filter(|x| x.is_ascii_alphabetic())
-                .collect();
-            match color_of_ball.as_str() {
-                "green" => sub_game.green = helper::convert_num_arr_to_num(count_of_ball),
-                "blue" => sub_game.blue = helper::convert_num_arr_to_num(count_of_ball),
-                "red" => sub_game.red = helper::convert_num_arr_to_num(count_of_ball),
-                _ => unreachable!()
-            }
+        bag.raise_to(&game.minimum_bag());
+    }
+    bag
+}
+
+/// The games `bag` cannot have produced, each with the first draw that rules it out.
+pub fn conflicting_games<'a>(games: &'a [GameInfo], bag: &CubeCounts) -> Vec<(&'a GameInfo, Violation)> {
+    games.iter()
+        .filter_map(|game| game.first_violation(bag).map(|violation| (game, violation)))
+        .collect()
+}
+
+/// The log likelihood of every game's draws coming from `bag`, see `GameInfo::log_likelihood`.
+pub fn log_likelihood(games: &[GameInfo], bag: &CubeCounts) -> f64 {
+    games.iter().map(|game| game.log_likelihood(bag)).sum()
+}
+
+/// `candidates` scored by `log_likelihood`, most likely first.
+pub fn rank_bags(games: &[GameInfo], candidates: &[CubeCounts]) -> Vec<(CubeCounts, f64)> {
+    let mut ranked: Vec<_> = candidates.iter()
+        .map(|bag| (bag.clone(), log_likelihood(games, bag)))
+        .collect();
+    ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    ranked
+}
+
+fn read_games<R: BufRead>(input: R, budget: &Budget) -> io::Result<Vec<GameInfo>> {
+    let mut games = Vec::new();
+    for (line_idx, line) in input.lines().enumerate() {
+        budget.step()?;
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        games.push(line.parse().map_err(|err| invalid_data(format!("line {}: {}", line_idx + 1, err)))?);
+    }
+    Ok(games)
+}
+
+/// Prints what the games say about the bag: the smallest bag fitting all of them,
+/// the games `bag` conflicts with, and how likely the draws are from each of the two bags.
+pub fn display<R: BufRead, W: Write>(input: R, bag: &CubeCounts, out: &mut W) -> io::Result<()> {
+    let games = read_games(input, &Budget::unlimited())?;
+    let minimum_bag = minimum_bag_for_all(&games);
+    writeln!(out, "Smallest bag for every game: {}", minimum_bag)?;
+    let conflicts = conflicting_games(&games, bag);
+    writeln!(out, "{} games conflict with {}", conflicts.len(), bag)?;
+    for (game, violation) in &conflicts {
+        writeln!(out, "  game {}: {}", game.id, violation)?;
+    }
+    for (candidate, score) in rank_bags(&games, &[bag.clone(), minimum_bag]) {
+        writeln!(out, "log likelihood {:.3} for {}", score, candidate)?;
+    }
+    Ok(())
+}
+
+impl FromStr for GameInfo {
+    type Err = ParseGameError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (game, draws) = line.split_once(':')
+            .ok_or_else(|| ParseGameError(format!("missing ':' in \"{}\"", line)))?;
+        let id = game.trim().strip_prefix("Game")
+            .and_then(|id| id.trim().parse().ok())
+            .ok_or_else(|| ParseGameError(format!("invalid game header \"{}\"", game)))?;
+        let sub_games = draws.split(';')
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(GameInfo { id, sub_games })
+    }
+}
+
+pub fn solve<R: BufRead>(input: R, bag: &CubeCounts, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
+    println!("Solving day 2 problems...");
+    let mut p_1_answer: u64 = 0;
+    let mut p_2_answer: u64 = 0;
+
+    for game_info in read_games(input, budget)? {
+        let violation = game_info.first_violation(bag);
+        let minimum_bag = game_info.minimum_bag();
+        trace.section(|| format!("game {}", game_info.id), |trace| {
+            trace.note(|| match &violation {
+                Some(violation) => format!("impossible, {}", violation),
+                None => String::from("possible"),
+            });
+            trace.note(|| format!("minimum bag {}, power {:?}", minimum_bag, minimum_bag.product()));
+        });
+        if violation.is_none() {
+            p_1_answer += u64::from(game_info.id);
+        }
+        p_2_answer = minimum_bag.product()
+            .and_then(|power| p_2_answer.checked_add(power))
+            .ok_or_else(|| invalid_data(format!("game {}: the sum of the powers does not fit in 64 bits", game_info.id)))?;
+    }
+
+    println!("The answer to the first part of day 2 problem is: {}", p_1_answer);
+    println!("The answer to the second part of day 2 problem is: {}", p_2_answer);
+    Ok(Answers::new(p_1_answer, p_2_answer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
+
+    fn parse_example() -> Vec<GameInfo> {
+        EXAMPLE.lines().map(|line| line.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_example() {
+        let games = parse_example();
+        let bag = CubeCounts::elf_bag();
+        let possible: u32 = games.iter().filter(|game| game.is_possible(&bag)).map(|game| game.id).sum();
+        let power: u64 = games.iter().map(|game| game.minimum_bag().product().unwrap()).sum();
+        assert_eq!(possible, 8);
+        assert_eq!(power, 2286);
+    }
+
+    #[test]
+    fn test_first_violation() {
+        let games = parse_example();
+        let violation = games[2].first_violation(&CubeCounts::elf_bag()).unwrap();
+        assert_eq!(violation, Violation { draw_idx: 0, colour: String::from("red"), drawn: 20, limit: 12 });
+    }
+
+    #[test]
+    fn test_extra_colours() {
+        let game: GameInfo = "Game 7: 2 violet, 1 red; 3 violet".parse().unwrap();
+        assert_eq!(game.minimum_bag(), "3 violet, 1 red".parse().unwrap());
+        assert!(!game.is_possible(&CubeCounts::elf_bag()));
+        assert!(game.is_possible(&CubeCounts::elf_bag().with("violet", 3)));
+        assert!("Game 8: 2 violet blue".parse::<GameInfo>().is_err());
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert!("Game 1: 4294967295 red, 1 red".parse::<GameInfo>().is_err());
+        let huge: CubeCounts = "4294967295 red, 4294967295 green, 4294967295 blue".parse().unwrap();
+        assert_eq!(huge.product(), None);
+        assert!(solve("Game 1: 4294967295 red, 4294967295 green, 4294967295 blue".as_bytes(),
+                      &CubeCounts::elf_bag(), &mut Trace::disabled(), &Budget::unlimited()).is_err());
+    }
+
+    #[test]
+    fn test_malformed_game_names_its_line() {
+        let err = solve("Game 1: 1 red\n\nGame 2 1 red".as_bytes(), &CubeCounts::elf_bag(), &mut Trace::disabled(), &Budget::unlimited()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 3: missing ':' in \"Game 2 1 red\"");
+    }
+
+    #[test]
+    fn test_bag_inference() {
+        let games = parse_example();
+        assert_eq!(minimum_bag_for_all(&games), "20 red, 13 green, 15 blue".parse().unwrap());
+        let conflicts: Vec<_> = conflicting_games(&games, &CubeCounts::elf_bag()).iter().map(|(game, _)| game.id).collect();
+        assert_eq!(conflicts, vec![3, 4]);
+        assert!(conflicting_games(&games, &minimum_bag_for_all(&games)).is_empty());
+    }
+
+    #[test]
+    fn test_log_likelihood() {
+        // One draw of 2 red and 1 blue from a bag of 1 red and 1 blue: 3 orderings at 1/8 each.
+        let game: GameInfo = "Game 1: 2 red, 1 blue".parse().unwrap();
+        let bag: CubeCounts = "1 red, 1 blue".parse().unwrap();
+        assert!((game.log_likelihood(&bag) - (3.0f64 / 8.0).ln()).abs() < 1e-12);
+        assert_eq!(game.log_likelihood(&"1 red".parse().unwrap()), f64::NEG_INFINITY);
+        let with_none_drawn: GameInfo = "Game 2: 2 red, 1 blue, 0 green".parse().unwrap();
+        assert_eq!(with_none_drawn.log_likelihood(&bag), game.log_likelihood(&bag));
+
+        let red_heavy: CubeCounts = "3 red, 1 blue".parse().unwrap();
+        let ranked = rank_bags(&[game], &[bag.clone(), red_heavy.clone()]);
+        assert_eq!(ranked[0].0, red_heavy);
+        assert_eq!(ranked[1].0, bag);
+    }
+
+    #[test]
+    fn test_ln_factorial() {
+        let exact = |n: u64| (2..=n).map(|i| (i as f64).ln()).sum::<f64>();
+        for n in [0, 1, 5, 255, 256, 257, 1000, 100_000] {
+            assert!((ln_factorial(n) - exact(n)).abs() <= 1e-12 * exact(n).max(1.0), "{}! is off", n);
         }
-        sub_games.push(sub_game);
     }
-    sub_games
-}
\ No newline at end of file
+}
@@ -1,102 +1,258 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::io;
 
-static DIRS: [(i64, i64); 8] = [(0, 1), (1, 0), (0, -1), (-1, 0), (1, 1), (-1, 1), (1, -1), (-1, -1)];
+use crate::answers::Answers;
+use crate::budget::Budget;
+use crate::explain::Trace;
+use crate::helper::invalid_data;
 
-pub fn solve(input: &str) {
+pub fn solve(input: &str, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
     println!("Solving day 3 problems...");
-    let lines = input.lines();
-    let mut arr = Vec::new();
-    for line in lines {
-        let parsed_line_arr = parse_line(line);
-        arr.push(parsed_line_arr);
-    }
-    println!("The solution to part one of day 3 is: {}", get_valid_machine_parts(&arr));
-    println!("The solution to part two of day 3 is: {}", get_total_gear_ratio(&arr));
+    let index = SchematicIndex::new(input, budget)?;
+    let overflow = || invalid_data(String::from("the sum overflows"));
+    let part_one_answer = trace.time_part(1, |trace| trace.section(|| String::from("part numbers"), |trace| get_valid_machine_parts(&index, trace))).ok_or_else(overflow)?;
+    let part_two_answer = trace.time_part(2, |trace| trace.section(|| String::from("gears"), |trace| get_total_gear_ratio(&index, trace))).ok_or_else(overflow)?;
+    println!("The solution to part one of day 3 is: {}", part_one_answer);
+    println!("The solution to part two of day 3 is: {}", part_two_answer);
+    Ok(Answers::new(part_one_answer, part_two_answer))
 }
 
-fn parse_line(line: &str) -> Vec<char> {
-    let mut line_arr = Vec::new();
-    for char in line.chars() {
-        line_arr.push(char);
-    }
-    line_arr
+/// A number in the schematic, `col_end` being exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberSpan {
+    pub row: usize,
+    pub col_start: usize,
+    pub col_end: usize,
+    pub value: u64,
 }
 
-fn get_valid_machine_parts(arr: &Vec<Vec<char>>) -> u64 {
-    let mut answer: u64 = 0;
-    let mut hash_set: HashSet<(usize, usize)> = HashSet::new();
-    for (outer_idx, char_arr) in arr.iter().enumerate() {
-        for (inner_idx, val) in char_arr.iter().enumerate() {
-            if !hash_set.contains(&(outer_idx, inner_idx)) && val.is_digit(10) && has_neighbor_symbol(inner_idx as i64, outer_idx as i64, &arr) {
-                answer += get_full_number(outer_idx, inner_idx, &arr[outer_idx], &mut hash_set);
+/// Any cell that is neither a digit nor `.`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol {
+    pub row: usize,
+    pub col: usize,
+    pub kind: char,
+}
+
+/// Every number and symbol of a schematic, read in one pass, and which of them touch.
+///
+/// The adjacency is bipartite and kept from both sides: `number_symbols[n]` are the
+/// indices into `symbols` touching `numbers[n]`, and `symbol_numbers` is its mirror.
+#[derive(Debug, Clone, Default)]
+pub struct SchematicIndex {
+    pub numbers: Vec<NumberSpan>,
+    pub symbols: Vec<Symbol>,
+    number_symbols: Vec<Vec<usize>>,
+    symbol_numbers: Vec<Vec<usize>>,
+}
+
+impl SchematicIndex {
+    pub fn new(input: &str, budget: &Budget) -> io::Result<Self> {
+        let mut index = SchematicIndex::default();
+        let mut symbol_at = HashMap::new();
+        for (row, line) in input.lines().enumerate() {
+            budget.step()?;
+            let mut current: Option<NumberSpan> = None;
+            for (col, c) in line.chars().enumerate() {
+                if let Some(digit) = c.to_digit(10) {
+                    let span = current.get_or_insert(NumberSpan { row, col_start: col, col_end: col, value: 0 });
+                    span.col_end = col + 1;
+                    span.value = span.value.checked_mul(10).and_then(|value| value.checked_add(digit as u64))
+                        .ok_or_else(|| invalid_data(format!("the number at row {}, column {} overflows", row + 1, span.col_start + 1)))?;
+                    continue;
+                }
+                index.numbers.extend(current.take());
+                if c != '.' {
+                    symbol_at.insert((row, col), index.symbols.len());
+                    index.symbols.push(Symbol { row, col, kind: c });
+                }
             }
+            index.numbers.extend(current);
         }
+
+        index.symbol_numbers = vec![Vec::new(); index.symbols.len()];
+        for (number_idx, span) in index.numbers.iter().enumerate() {
+            budget.step()?;
+            let mut touching = Vec::new();
+            for row in span.row.saturating_sub(1)..=span.row + 1 {
+                for col in span.col_start.saturating_sub(1)..=span.col_end {
+                    if let Some(&symbol_idx) = symbol_at.get(&(row, col)) {
+                        touching.push(symbol_idx);
+                        index.symbol_numbers[symbol_idx].push(number_idx);
+                    }
+                }
+            }
+            index.number_symbols.push(touching);
+        }
+        Ok(index)
     }
-    answer
-}
 
-fn has_neighbor_symbol(inner_idx: i64, outer_idx: i64, arr: &Vec<Vec<char>>) -> bool {
-    let n = arr.len() as i64;
-    let m = arr[0].len() as i64;
-    for (dx, dy) in DIRS {
-        let x = outer_idx + dx;
-        let y = inner_idx + dy;
-        if 0 <= x && x < n && 0 <= y && y < m && !arr[x as usize][y as usize].is_digit(10) && !(arr[x as usize][y as usize] == '.') {
-            return true;
-        };
-    };
-    false
-}
+    /// The symbols touching `numbers[number_idx]`.
+    pub fn symbols_of(&self, number_idx: usize) -> impl Iterator<Item=&Symbol> {
+        self.number_symbols[number_idx].iter().map(|&idx| &self.symbols[idx])
+    }
 
-fn get_full_number(outer_idx: usize, inner_idx: usize, arr: &Vec<char>, hash_set: &mut HashSet<(usize, usize)>) -> u64 {
-    let mut answer = 0;
-    let mut start = inner_idx;
+    /// The numbers touching `symbols[symbol_idx]`.
+    pub fn numbers_of(&self, symbol_idx: usize) -> impl Iterator<Item=&NumberSpan> {
+        self.symbol_numbers[symbol_idx].iter().map(|&idx| &self.numbers[idx])
+    }
 
-    while (start as i64 - 1) >= 0 && arr[start - 1].is_digit(10) {
-        start -= 1;
-    };
+    /// Numbers touching at least one symbol.
+    pub fn part_numbers(&self) -> impl Iterator<Item=&NumberSpan> {
+        self.numbers.iter().zip(&self.number_symbols)
+            .filter(|(_, symbols)| !symbols.is_empty())
+            .map(|(span, _)| span)
+    }
 
+    /// Indices of the symbols of `kind`, or of any kind when `None`, touching exactly `degree` numbers.
+    pub fn symbols_with_degree(&self, kind: Option<char>, degree: usize) -> impl Iterator<Item=usize> + '_ {
+        (0..self.symbols.len()).filter(move |&idx| {
+            kind.is_none_or(|kind| self.symbols[idx].kind == kind) && self.symbol_numbers[idx].len() == degree
+        })
+    }
 
-    let mut end = inner_idx;
-    while end < arr.len() && arr[end].is_digit(10) {
-        end += 1;
-    };
+    /// Every number touching a symbol of `kind` that has exactly `degree` numbers around it, in reading order.
+    pub fn numbers_touching(&self, kind: Option<char>, degree: usize) -> Vec<&NumberSpan> {
+        let mut number_indices: Vec<usize> = self.symbols_with_degree(kind, degree)
+            .flat_map(|idx| self.symbol_numbers[idx].iter().copied())
+            .collect();
+        number_indices.sort_unstable();
+        number_indices.dedup();
+        number_indices.into_iter().map(|idx| &self.numbers[idx]).collect()
+    }
 
-    for idx in start..end {
-        answer = answer * 10 + arr[idx].to_digit(10).unwrap() as u64;
-        hash_set.insert((outer_idx, idx));
+    /// Symbols touching exactly two numbers. Like the original solution, any symbol counts, not just `*`.
+    pub fn gears(&self) -> impl Iterator<Item=usize> + '_ {
+        self.symbols_with_degree(None, 2)
     }
-    answer
 }
 
+const CELL_WIDTH: usize = 10;
+const CELL_HEIGHT: usize = 16;
 
-fn get_total_gear_ratio(arr: &Vec<Vec<char>>) -> u64 {
-    let mut answer: u64 = 0;
-    for (outer_idx, char_arr) in arr.iter().enumerate() {
-        for (inner_idx, val) in char_arr.iter().enumerate() {
-            if !val.is_digit(10) && *val != '.' {
-                answer += get_gear_ratio(inner_idx as i64, outer_idx as i64, &arr);
+/// The schematic as an SVG grid. Part numbers are green, numbers touching no symbol
+/// are red, and every gear is gold with its two numbers outlined and linked to it.
+pub fn to_svg(input: &str) -> io::Result<String> {
+    let index = SchematicIndex::new(input, &Budget::unlimited())?;
+    let width = input.lines().map(|line| line.chars().count()).max().unwrap_or(0) * CELL_WIDTH;
+    let height = input.lines().count() * CELL_HEIGHT;
+
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"{}\">\n", width, height, CELL_HEIGHT - 4);
+    svg.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n", width, height));
+    for (number_idx, span) in index.numbers.iter().enumerate() {
+        let is_part = index.symbols_of(number_idx).next().is_some();
+        let fill = if is_part { "palegreen" } else { "lightcoral" };
+        svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                              span.col_start * CELL_WIDTH, span.row * CELL_HEIGHT, (span.col_end - span.col_start) * CELL_WIDTH, CELL_HEIGHT, fill));
+    }
+    for symbol_idx in index.gears() {
+        let Symbol { row, col, .. } = index.symbols[symbol_idx];
+        svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"gold\"/>\n",
+                              col * CELL_WIDTH, row * CELL_HEIGHT, CELL_WIDTH, CELL_HEIGHT));
+        for span in index.numbers_of(symbol_idx) {
+            svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"goldenrod\" stroke-width=\"2\"/>\n",
+                                  span.col_start * CELL_WIDTH, span.row * CELL_HEIGHT, (span.col_end - span.col_start) * CELL_WIDTH, CELL_HEIGHT));
+            svg.push_str(&format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"goldenrod\"/>\n",
+                                  col * CELL_WIDTH + CELL_WIDTH / 2, row * CELL_HEIGHT + CELL_HEIGHT / 2,
+                                  (span.col_start + span.col_end) * CELL_WIDTH / 2, span.row * CELL_HEIGHT + CELL_HEIGHT / 2));
+        }
+    }
+    for (row, line) in input.lines().enumerate() {
+        for (col, val) in line.chars().enumerate() {
+            if val == '.' {
+                continue;
             }
+            let text = match val {
+                '&' => String::from("&amp;"),
+                '<' => String::from("&lt;"),
+                '>' => String::from("&gt;"),
+                val => val.to_string(),
+            };
+            svg.push_str(&format!("<text x=\"{}\" y=\"{}\">{}</text>\n", col * CELL_WIDTH + 1, (row + 1) * CELL_HEIGHT - 4, text));
         }
     }
-    answer
+    svg.push_str("</svg>\n");
+    Ok(svg)
 }
 
-fn get_gear_ratio(inner_idx: i64, outer_idx: i64, arr: &Vec<Vec<char>>) -> u64 {
-    let n = arr.len() as i64;
-    let m = arr[0].len() as i64;
-    let mut hash_set: HashSet<(usize, usize)> = HashSet::new();
-    let mut cnt = 0;
-    let mut answer = 1;
-    for (dx, dy) in DIRS {
-        let x = outer_idx + dx;
-        let y = inner_idx + dy;
-        if 0 <= x && x < n && 0 <= y && y < m && !hash_set.contains(&(x as usize, y as usize)) && arr[x as usize][y as usize].is_digit(10) {
-            cnt += 1;
-            answer *= get_full_number(x as usize, y as usize, &arr[x as usize], &mut hash_set)
-        };
-    };
-    if cnt == 2 {
-        return answer;
-    } else { 0 }
-}
\ No newline at end of file
+fn get_valid_machine_parts(index: &SchematicIndex, trace: &mut Trace) -> Option<u64> {
+    let mut answer: u64 = 0;
+    for span in index.part_numbers() {
+        trace.note(|| format!("{} at row {}, column {}", span.value, span.row + 1, span.col_start + 1));
+        answer = answer.checked_add(span.value)?;
+    }
+    Some(answer)
+}
+
+fn get_total_gear_ratio(index: &SchematicIndex, trace: &mut Trace) -> Option<u64> {
+    let mut answer: u64 = 0;
+    for symbol_idx in index.gears() {
+        let symbol = index.symbols[symbol_idx];
+        let numbers: Vec<u64> = index.numbers_of(symbol_idx).map(|span| span.value).collect();
+        trace.note(|| format!("{} at row {}, column {}: {} * {}", symbol.kind, symbol.row + 1, symbol.col + 1, numbers[0], numbers[1]));
+        answer = answer.checked_add(numbers[0].checked_mul(numbers[1])?)?;
+    }
+    Some(answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..";
+
+    #[test]
+    fn test_svg_export() {
+        let svg = to_svg(EXAMPLE).unwrap();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"160\""));
+        assert_eq!(svg.matches("fill=\"palegreen\"").count(), 8);
+        assert_eq!(svg.matches("fill=\"lightcoral\"").count(), 2);
+        assert_eq!(svg.matches("fill=\"gold\"").count(), 2);
+        assert_eq!(svg.matches("<line").count(), 4);
+    }
+
+    #[test]
+    fn test_index() {
+        let index = SchematicIndex::new(EXAMPLE, &Budget::unlimited()).unwrap();
+        assert_eq!(index.numbers.len(), 10);
+        assert_eq!(index.symbols.len(), 6);
+        assert_eq!(index.numbers[0], NumberSpan { row: 0, col_start: 0, col_end: 3, value: 467 });
+        assert_eq!(index.part_numbers().map(|span| span.value).sum::<u64>(), 4361);
+        assert_eq!(get_total_gear_ratio(&index, &mut Trace::disabled()), Some(467835));
+
+        let lone_star = index.symbols_with_degree(Some('*'), 1).collect::<Vec<_>>();
+        assert_eq!(lone_star.len(), 1);
+        assert_eq!(index.symbols[lone_star[0]], Symbol { row: 4, col: 3, kind: '*' });
+        let values: Vec<u64> = index.numbers_touching(Some('*'), 2).iter().map(|span| span.value).collect();
+        assert_eq!(values, vec![467, 35, 755, 598]);
+    }
+
+    #[test]
+    fn test_number_at_line_end_touches_symbol_below() {
+        let index = SchematicIndex::new("..12\n...#", &Budget::unlimited()).unwrap();
+        assert_eq!(index.numbers[0], NumberSpan { row: 0, col_start: 2, col_end: 4, value: 12 });
+        assert_eq!(index.symbols_of(0).collect::<Vec<_>>(), vec![&Symbol { row: 1, col: 3, kind: '#' }]);
+    }
+
+    #[test]
+    fn test_overflow_is_an_error() {
+        assert!(SchematicIndex::new("99999999999999999999*", &Budget::unlimited()).is_err());
+        let index = SchematicIndex::new("9999999999*9999999999", &Budget::unlimited()).unwrap();
+        assert_eq!(get_total_gear_ratio(&index, &mut Trace::disabled()), None);
+    }
+
+    #[test]
+    fn test_budget_stops_the_index() {
+        let err = solve(EXAMPLE, &mut Trace::disabled(), &Budget::unlimited().with_step_limit(5)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
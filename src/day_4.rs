@@ -1,5 +1,10 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::io::{self, BufRead};
 
+use crate::answers::Answers;
+use crate::budget::Budget;
+use crate::explain::Trace;
 use crate::helper as helper;
 
 #[derive(Debug)]
@@ -10,67 +15,229 @@ struct CardGame {
 }
 
 impl CardGame {
-    fn calculate_points(&self) -> u32 {
-        let matching = self.winning_numbers.intersection(&self.numbers_in_possession).count() as u32;
-        if matching > 0 {
-            1 << (matching - 1)
-        } else {
-            0
-        }
+    fn matching_numbers(&self) -> u32 {
+        self.winning_numbers.intersection(&self.numbers_in_possession).count() as u32
     }
+}
 
-    fn calculate_scratch_cards_won(&self) -> u32 {
-        self.winning_numbers.intersection(&self.numbers_in_possession).count() as u32
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseCardError {
+    Malformed(String),
+    MissingCard { expected: u32, found: u32 },
+    /// The card's points or the pile's card count no longer fit in their integer type.
+    Overflow(u32),
+}
+
+impl fmt::Display for ParseCardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseCardError::Malformed(line) => write!(f, "malformed card \"{}\"", line),
+            ParseCardError::MissingCard { expected, found } => write!(f, "expected card {} but found card {}", expected, found),
+            ParseCardError::Overflow(id) => write!(f, "card {} wins more cards than can be counted", id),
+        }
     }
 }
 
-pub fn solve(input: &str) {
-    println!("Solving day 4 problems...");
-    let lines = input.lines();
-    let mut cards: Vec<CardGame> = Vec::new();
-    let mut p_1_answer = 0;
-    let mut p_2_answer = 0;
-    let mut scratch_cards_map: HashMap<u32, u32> = HashMap::new();
-
-    for line in lines {
-        cards.push(parse_line_for_card_game(line));
-    }
-    for card in cards.iter() {
-        p_1_answer += card.calculate_points()
-    }
-
-    cards.reverse();
-    for card in cards {
-        p_2_answer+=1;
-        let mut won = card.calculate_scratch_cards_won();
-        let max_card_id = card.id+won;
-        for next_card_id in card.id+1..=max_card_id {
-            won += scratch_cards_map.get(&next_card_id).unwrap_or(&0);
+impl std::error::Error for ParseCardError {}
+
+/// Every scratchcard of a table, parsed once into its match count.
+///
+/// Copies are settled as cards are added: a card can only be won from the cards
+/// before it, so its count is final by the time it is pushed.
+#[derive(Debug, Clone, Default)]
+pub struct ScratchcardPile {
+    matches: Vec<u32>,
+    copies: Vec<u64>,
+    total_cards: u64,
+    // Extra copies owed to the cards not pushed yet, front is the next card.
+    pending_copies: VecDeque<u64>,
+}
+
+impl ScratchcardPile {
+    pub fn read<R: BufRead>(input: R, budget: &Budget) -> io::Result<Self> {
+        let mut pile = ScratchcardPile::default();
+        for (line_idx, line) in input.lines().enumerate() {
+            budget.step()?;
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            pile.push_line(line)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_idx + 1, err)))?;
+        }
+        Ok(pile)
+    }
+
+    /// Adds the next card, which must be numbered right after the last one.
+    pub fn push_line(&mut self, line: &str) -> Result<(), ParseCardError> {
+        let card = parse_line_for_card_game(line)?;
+        let expected = self.len() as u32 + 1;
+        if card.id != expected {
+            return Err(ParseCardError::MissingCard { expected, found: card.id });
         }
-        scratch_cards_map.insert(card.id, won);
-        p_2_answer+=won;
+
+        let won = card.matching_numbers();
+        let overflow = ParseCardError::Overflow(card.id);
+        // Points double with every match and must stay within `u32`.
+        if won > u32::BITS {
+            return Err(overflow);
+        }
+        let copies = self.pending_copies.pop_front().unwrap_or(0).checked_add(1).ok_or(overflow.clone())?;
+        self.total_cards = self.total_cards.checked_add(copies).ok_or(overflow.clone())?;
+        if self.pending_copies.len() < won as usize {
+            self.pending_copies.resize(won as usize, 0);
+        }
+        for pending in self.pending_copies.iter_mut().take(won as usize) {
+            *pending = pending.checked_add(copies).ok_or(overflow.clone())?;
+        }
+        self.matches.push(won);
+        self.copies.push(copies);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    /// How many of card `id`'s numbers are winning numbers.
+    pub fn matches(&self, id: u32) -> Option<u32> {
+        self.matches.get((id as usize).checked_sub(1)?).copied()
+    }
+
+    pub fn points(&self, id: u32) -> Option<u32> {
+        self.matches(id).map(|matching| if matching > 0 { 1 << (matching - 1) } else { 0 })
+    }
+
+    /// How many instances of card `id` end up in the pile, the original included.
+    pub fn copies(&self, id: u32) -> Option<u64> {
+        self.copies.get((id as usize).checked_sub(1)?).copied()
     }
 
+    pub fn total_points(&self) -> u64 {
+        (1..=self.len() as u32).filter_map(|id| self.points(id)).map(u64::from).sum()
+    }
+
+    pub fn total_cards(&self) -> u64 {
+        self.total_cards
+    }
+}
+
+pub fn solve<R: BufRead>(input: R, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
+    println!("Solving day 4 problems...");
+    let pile = ScratchcardPile::read(input, budget)?;
+    for id in 1..=pile.len() as u32 {
+        trace.note(|| format!("card {}: {} matches, {} points, {} copies",
+                              id, pile.matches(id).unwrap(), pile.points(id).unwrap(), pile.copies(id).unwrap()));
+    }
 
-    println!("The solution to part one of day 3 is: {}", p_1_answer);
-    println!("The solution to part two of day 3 is: {}", p_2_answer);
+    println!("The solution to part one of day 4 is: {}", pile.total_points());
+    println!("The solution to part two of day 4 is: {}", pile.total_cards());
+    Ok(Answers::new(pile.total_points(), pile.total_cards()))
 }
 
-fn parse_line_for_card_game(line: &str) -> CardGame {
-    let line_substr: Vec<_> = line.split(":").collect();
-    let game_id: Vec<u32> = helper::filter_digits_from_str(line_substr[0]);
-    let games: Vec<_> = line_substr[1].split("|").collect();
+fn parse_line_for_card_game(line: &str) -> Result<CardGame, ParseCardError> {
+    let malformed = || ParseCardError::Malformed(String::from(line));
+    let (card, numbers) = line.split_once(':').ok_or_else(malformed)?;
+    let id = card.trim().strip_prefix("Card")
+        .and_then(|id| id.trim().parse().ok())
+        .ok_or_else(malformed)?;
+    let (winning, in_possession) = numbers.split_once('|').ok_or_else(malformed)?;
     let mut winning_numbers = HashSet::new();
     let mut numbers_in_possession = HashSet::new();
-    for num in helper::get_num_arr_from_space_seperated_str(games[0]) {
+    for num in helper::get_num_arr_from_space_seperated_str(winning) {
         winning_numbers.insert(num);
     }
-    for num in helper::get_num_arr_from_space_seperated_str(games[1]) {
+    for num in helper::get_num_arr_from_space_seperated_str(in_possession) {
         numbers_in_possession.insert(num);
     }
-    CardGame {
-        id: helper::convert_num_arr_to_num(game_id),
+    Ok(CardGame {
+        id,
         winning_numbers,
         numbers_in_possession,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const EXAMPLE: &str = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
+    #[test]
+    fn test_example() {
+        let pile = ScratchcardPile::read(EXAMPLE.as_bytes(), &Budget::unlimited()).unwrap();
+        assert_eq!(pile.total_points(), 13);
+        assert_eq!(pile.total_cards(), 30);
+        assert_eq!(pile.points(1), Some(8));
+        assert_eq!(pile.copies(4), Some(8));
+        assert_eq!(pile.copies(7), None);
+    }
+
+    #[test]
+    fn test_missing_card() {
+        let mut pile = ScratchcardPile::default();
+        pile.push_line("Card 1: 41 48 | 41 2").unwrap();
+        assert_eq!(pile.push_line("Card 3: 1 2 | 3 4"), Err(ParseCardError::MissingCard { expected: 2, found: 3 }));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_overflow() {
+        let numbers: Vec<String> = (1..=33).map(|number| number.to_string()).collect();
+        let numbers = numbers.join(" ");
+        let mut pile = ScratchcardPile::default();
+        assert_eq!(pile.push_line(&format!("Card 1: {} | {}", numbers, numbers)), Err(ParseCardError::Overflow(1)));
+
+        // Every card winning the next two doubles the copies each time, until they no longer fit.
+        let mut pile = ScratchcardPile::default();
+        let result = (1..=100).try_for_each(|id| pile.push_line(&format!("Card {}: 1 2 | 1 2", id)));
+        assert!(matches!(result, Err(ParseCardError::Overflow(_))));
+    }
+
+    #[test]
+    fn test_budget_stops_the_reading() {
+        let err = ScratchcardPile::read(EXAMPLE.as_bytes(), &Budget::unlimited().with_step_limit(3)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    /// A card line with `matches` of its five winning numbers among the numbers in possession.
+    fn card_line(id: usize, matches: usize) -> String {
+        let in_possession: Vec<String> = (1..=matches).chain(50..55 - matches).map(|number| number.to_string()).collect();
+        format!("Card {}: 1 2 3 4 5 | {}", id, in_possession.join(" "))
+    }
+
+    /// Scratches every card instance one at a time, literally adding the copies it wins to the pile.
+    fn copy_cards(matches: &[usize]) -> Vec<u64> {
+        let mut copies = vec![0; matches.len()];
+        let mut pile: Vec<usize> = (0..matches.len()).collect();
+        while let Some(card) = pile.pop() {
+            copies[card] += 1;
+            pile.extend((card + 1..=card + matches[card]).filter(|&won| won < matches.len()));
+        }
+        copies
+    }
+
+    proptest! {
+        #[test]
+        fn test_cascade_matches_copying(matches in prop::collection::vec(0..=3usize, 0..12)) {
+            let input: Vec<String> = matches.iter().enumerate().map(|(idx, &won)| card_line(idx + 1, won)).collect();
+            let pile = ScratchcardPile::read(input.join("\n").as_bytes(), &Budget::unlimited()).unwrap();
+            let copies = copy_cards(&matches);
+            for (idx, &expected) in copies.iter().enumerate() {
+                prop_assert_eq!(pile.copies(idx as u32 + 1), Some(expected));
+            }
+            prop_assert_eq!(pile.total_cards(), copies.iter().sum::<u64>());
+        }
+    }
+}
@@ -1,146 +1,318 @@
 use std::cmp::min;
-use std::collections::HashMap;
+use std::cell::OnceCell;
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+
+use crate::answers::Answers;
+use crate::budget::Budget;
+use crate::explain::Trace;
+use crate::helper::invalid_data;
+
+static STAGES: [&str; 7] = ["seed-to-soil", "soil-to-fertilizer", "fertilizer-to-water", "water-to-light",
+    "light-to-temperature", "temperature-to-humidity", "humidity-to-location"];
+
+/// Inclusive `(start, end)` intervals of seeds, or of whatever a stage mapped them to.
+type SeedRanges = Vec<(u64, u64)>;
 
 #[derive(Debug, Clone)]
 struct RangeSet {
     ranges: Vec<(u64, u64, u64)>,
+    // The lines as disjoint pieces sorted by source, worked out on first use.
+    pieces: OnceCell<Vec<MapPiece>>,
+}
+
+/// A stretch of source values that one map line sends on, `source` being inclusive and
+/// `dest_start` where `source.0` ends up.
+#[derive(Debug, Clone, Copy)]
+struct MapPiece {
+    source: (u64, u64),
+    dest_start: u64,
+    line_idx: usize,
 }
 
 impl RangeSet {
     fn new() -> Self {
-        RangeSet { ranges: Vec::new() }
+        RangeSet { ranges: Vec::new(), pieces: OnceCell::new() }
     }
 
     fn insert(&mut self, source_start: u64, dest_start: u64, range_length: u64) {
         self.ranges.push((source_start, dest_start, range_length));
+        self.pieces.take();
     }
 
-    fn get(&self, start: u64, end: u64) -> Vec<(u64, u64)> {
+    fn get(&self, start: u64, end: u64, trace: &mut Trace, budget: &Budget) -> io::Result<Vec<(u64, u64)>> {
         let mut new_ranges = Vec::new();
+        for fragment in self.get_fragments(start, end, budget)? {
+            trace.note(|| match fragment.line_idx {
+                Some(line_idx) => {
+                    let (source_start, dest_start, range_length) = self.ranges[line_idx];
+                    format!("[{}, {}] -> [{}, {}] via {} {} {}", fragment.source.0, fragment.source.1,
+                            fragment.dest.0, fragment.dest.1, dest_start, source_start, range_length)
+                }
+                None => format!("[{}, {}] unmapped", fragment.source.0, fragment.source.1),
+            });
+            new_ranges.push(fragment.dest);
+        }
+        Ok(new_ranges)
+    }
+
+    /// The map lines cut into disjoint pieces sorted by source. Where lines overlap the
+    /// earlier one wins, the way looking a value up line by line would find it.
+    fn pieces(&self) -> &[MapPiece] {
+        self.pieces.get_or_init(|| {
+            // Where each line starts and where it stops covering, one past its end.
+            let mut events: Vec<(u128, usize)> = Vec::with_capacity(2 * self.ranges.len());
+            for (line_idx, &(source_start, _, range_length)) in self.ranges.iter().enumerate() {
+                events.push((source_start as u128, line_idx));
+                events.push((source_start as u128 + range_length as u128, line_idx));
+            }
+            events.sort_unstable();
 
-        for &(source_start, dest_start, range_length) in &self.ranges {
-            let source_end = source_start + range_length - 1;
-            let dest_end = dest_start + range_length - 1;
-            if source_start <= start && start <= source_end && end > source_end {
-                //Case where start is in range but end is not
-                let new_start = dest_start + (start - source_start);
-                new_ranges.push((new_start, dest_end));
-                new_ranges.extend(self.get(source_end + 1, end));
-                return new_ranges;
-            } else if source_start > start && source_start <= end && end <= source_end {
-                //Case where start is not in range but end is
-                let new_end = dest_start + (end - source_start);
-                new_ranges.push((dest_start, new_end));
-                new_ranges.extend(self.get(start, source_start - 1));
-                return new_ranges;
-            } else if source_start <= start && end <= source_end {
-                //Case where end and start are all in range
-                let new_start = dest_start + (start - source_start);
-                let new_end = dest_start + (end - source_start);
-                new_ranges.push((new_start, new_end));
-                return new_ranges;
+            let mut pieces: Vec<MapPiece> = Vec::new();
+            let mut active = BTreeSet::new();
+            for (event_idx, &(position, line_idx)) in events.iter().enumerate() {
+                if !active.remove(&line_idx) {
+                    active.insert(line_idx);
+                }
+                let Some(&(next, _)) = events.get(event_idx + 1) else {
+                    break;
+                };
+                let Some(&line_idx) = active.first() else {
+                    continue;
+                };
+                if position == next {
+                    continue;
+                }
+                let (source_start, dest_start, _) = self.ranges[line_idx];
+                let (start, end) = (position as u64, (next - 1) as u64);
+                match pieces.last_mut() {
+                    Some(last) if last.line_idx == line_idx && last.source.1 + 1 == start => last.source.1 = end,
+                    _ => pieces.push(MapPiece { source: (start, end), dest_start: dest_start + (start - source_start), line_idx }),
+                }
             }
+            pieces
+        })
+    }
+
+    /// Splits `[start, end]` into the pieces the map lines send to different places, in order.
+    fn get_fragments(&self, start: u64, end: u64, budget: &Budget) -> io::Result<Vec<Fragment>> {
+        let pieces = self.pieces();
+        let mut fragments = Vec::new();
+        // The first value not split off yet.
+        let mut next = start;
+        let first = pieces.partition_point(|piece| piece.source.1 < start);
+        for piece in &pieces[first..] {
+            budget.step()?;
+            if piece.source.0 > end {
+                break;
+            }
+            let (from, to) = (piece.source.0.max(next), piece.source.1.min(end));
+            if next < from {
+                budget.step()?;
+                fragments.push(Fragment { source: (next, from - 1), dest: (next, from - 1), line_idx: None });
+            }
+            let dest_start = piece.dest_start + (from - piece.source.0);
+            fragments.push(Fragment { source: (from, to), dest: (dest_start, dest_start + (to - from)), line_idx: Some(piece.line_idx) });
+            if to == end {
+                return Ok(fragments);
+            }
+            next = to + 1;
         }
-        // Case 4: No overlapping ranges found, return the input range
-        new_ranges.push((start, end));
-        new_ranges
+        budget.step()?;
+        fragments.push(Fragment { source: (next, end), dest: (next, end), line_idx: None });
+        Ok(fragments)
     }
 }
 
-pub fn solve(input: &str) {
+/// A piece of an interval and where one map sent it, `line_idx` being the map line it matched.
+#[derive(Debug, Clone, Copy)]
+struct Fragment {
+    source: (u64, u64),
+    dest: (u64, u64),
+    line_idx: Option<usize>,
+}
+
+pub fn solve(input: &str, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
     println!("Solving Day 5 problems...");
-    let (part_1_seeds, part_2_seeds, sections) = parse_input(input);
+    let (part_1_seeds, part_2_seeds, sections) = parse_input(input)?;
 
-    let p1_solution = get_lowest_location_from_seeds(&part_1_seeds, &sections);
+    let p1_solution = trace.time_part(1, |trace| trace.section(|| String::from("part one"), |trace| get_lowest_location_from_seeds(&part_1_seeds, &sections, trace, budget)))?;
     println!("The answer to the first gold star for day 5 is: {}", p1_solution);
 
-    let p2_solution = get_lowest_location_from_seeds(&part_2_seeds, &sections);
+    let p2_solution = trace.time_part(2, |trace| trace.section(|| String::from("part two"), |trace| get_lowest_location_from_seeds(&part_2_seeds, &sections, trace, budget)))?;
     println!("The answer to the second gold star for day 5 is: {}", p2_solution);
+    Ok(Answers::new(p1_solution, p2_solution))
+}
+
+const COLUMN_WIDTH: usize = 260;
+const BOX_WIDTH: usize = 190;
+const ROW_HEIGHT: usize = 28;
+const HEADER_HEIGHT: usize = 30;
+
+/// One interval in the range flow, remembering which interval of the previous stage it came from.
+struct FlowInterval {
+    range: (u64, u64),
+    parent: Option<(usize, Fragment)>,
+}
+
+/// The part two seed intervals flowing through every map as an SVG diagram.
+///
+/// Each column holds the intervals of one category. An edge leads from an interval to
+/// each of its fragments, labelled with the map line that moved it, and hovering it shows
+/// the exact source range. The intervals leading to the lowest location are gold.
+pub fn to_svg(input: &str) -> io::Result<String> {
+    let (_, part_2_seeds, sections) = parse_input(input)?;
+    let mut columns: Vec<Vec<FlowInterval>> = vec![part_2_seeds.iter().map(|&range| FlowInterval { range, parent: None }).collect()];
+    for stage in STAGES {
+        let range_set = sections.get(stage).unwrap();
+        let mut next_column = Vec::new();
+        for (parent_idx, interval) in columns.last().unwrap().iter().enumerate() {
+            let fragments = range_set.get_fragments(interval.range.0, interval.range.1, &Budget::unlimited())?;
+            next_column.extend(fragments.into_iter().map(|fragment| FlowInterval { range: fragment.dest, parent: Some((parent_idx, fragment)) }));
+        }
+        columns.push(next_column);
+    }
+
+    let mut lowest_path = vec![None; columns.len()];
+    let lowest = columns.last().unwrap().iter().enumerate().min_by_key(|(_, interval)| interval.range.0).map(|(idx, _)| idx);
+    let mut current = lowest;
+    for column_idx in (0..columns.len()).rev() {
+        lowest_path[column_idx] = current;
+        current = current.and_then(|idx| columns[column_idx][idx].parent.map(|(parent_idx, _)| parent_idx));
+    }
+
+    let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+    let width = columns.len() * COLUMN_WIDTH;
+    let height = HEADER_HEIGHT + rows * ROW_HEIGHT;
+    let box_y = |row: usize| HEADER_HEIGHT + row * ROW_HEIGHT;
+
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"11\">\n", width, height);
+    svg.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n", width, height));
+    for (column_idx, column) in columns.iter().enumerate() {
+        let x = column_idx * COLUMN_WIDTH;
+        let category = if column_idx == 0 { "seed" } else { STAGES[column_idx - 1].rsplit("-to-").next().unwrap() };
+        svg.push_str(&format!("<text x=\"{}\" y=\"20\" font-weight=\"bold\">{}</text>\n", x + 5, category));
+
+        for (row, interval) in column.iter().enumerate() {
+            let is_lowest_path = lowest_path[column_idx] == Some(row);
+            if let Some((parent_idx, fragment)) = interval.parent {
+                let (x1, y1) = (x - COLUMN_WIDTH + BOX_WIDTH, box_y(parent_idx) + ROW_HEIGHT / 2 - 2);
+                let (x2, y2) = (x, box_y(row) + ROW_HEIGHT / 2 - 2);
+                let stroke = if is_lowest_path { "goldenrod" } else { "gray" };
+                let label = fragment.line_idx.map_or(String::from("unmapped"), |line_idx| format!("line {}", line_idx + 1));
+                svg.push_str(&format!("<g><title>[{}, {}] {}</title>\n", fragment.source.0, fragment.source.1, label));
+                svg.push_str(&format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\"/>\n", x1, y1, x2, y2, stroke));
+                svg.push_str(&format!("<text x=\"{}\" y=\"{}\" font-size=\"9\" fill=\"dimgray\">{}</text></g>\n", x - 50, y2 - 3, label));
+            }
+            let fill = if is_lowest_path { "gold" } else { "lightsteelblue" };
+            svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n", x, box_y(row), BOX_WIDTH, ROW_HEIGHT - 4, fill));
+            svg.push_str(&format!("<text x=\"{}\" y=\"{}\">[{}, {}]</text>\n", x + 4, box_y(row) + ROW_HEIGHT / 2 + 2, interval.range.0, interval.range.1));
+        }
+    }
+    svg.push_str("</svg>\n");
+    Ok(svg)
 }
 
-fn parse_input(input: &str) -> (Vec<(u64, u64)>, Vec<(u64, u64)>, HashMap<String, RangeSet>) {
+/// The seeds for both parts and every map by name. All seven maps must be present.
+fn parse_input(input: &str) -> io::Result<(SeedRanges, SeedRanges, HashMap<String, RangeSet>)> {
     let mut part_1_seeds = Vec::new();
     let mut part_2_seeds = Vec::new();
     let mut sections = HashMap::new();
     let mut current_section = String::new();
 
-    let mut lines = input.lines();
-    while let Some(line) = lines.next() {
+    for line in input.lines() {
         if line.starts_with("seeds:") {
-            part_1_seeds = parse_part_1_seeds(line);
-            part_2_seeds = parse_part_2_seeds(line);
+            part_1_seeds = parse_part_1_seeds(line)?;
+            part_2_seeds = parse_part_2_seeds(line)?;
         } else if line.ends_with("map:") {
             if !current_section.is_empty() {
-                let (key, value) = parse_section(&current_section.clone());
+                let (key, value) = parse_section(&current_section)?;
                 sections.insert(key, value);
                 current_section.clear();
             }
             current_section = String::from(line);
         } else {
-            current_section.push_str("\n");
+            current_section.push('\n');
             current_section.push_str(line);
         }
     }
     if !current_section.is_empty() {
-        let (key, value) = parse_section(&current_section.clone());
+        let (key, value) = parse_section(&current_section)?;
         sections.insert(key, value);
     }
+    if let Some(stage) = STAGES.iter().find(|stage| !sections.contains_key(**stage)) {
+        return Err(invalid_data(format!("missing the {} map", stage)));
+    }
 
-    (part_1_seeds, part_2_seeds, sections)
+    Ok((part_1_seeds, part_2_seeds, sections))
 }
 
-fn parse_part_1_seeds(input: &str) -> Vec<(u64, u64)> {
-    let mut seeds = Vec::new();
-    let values = input.split_whitespace().skip(1).map(|s| s.parse::<u64>().unwrap());
-    for val in values {
-        seeds.push((val, val));
-    }
-    seeds
+fn parse_values(input: &str) -> io::Result<Vec<u64>> {
+    input.split_whitespace()
+        .map(|s| s.parse::<u64>().map_err(|_| invalid_data(format!("invalid number \"{}\"", s))))
+        .collect()
+}
+
+fn parse_part_1_seeds(input: &str) -> io::Result<Vec<(u64, u64)>> {
+    let values = parse_values(input.trim_start_matches("seeds:"))?;
+    Ok(values.into_iter().map(|val| (val, val)).collect())
 }
 
-fn parse_part_2_seeds(input: &str) -> Vec<(u64, u64)> {
+fn parse_part_2_seeds(input: &str) -> io::Result<Vec<(u64, u64)>> {
     let mut seeds = Vec::new();
-    let mut values = input.split_whitespace().skip(1).map(|s| s.parse::<u64>().unwrap());
-    while let (Some(val1), Some(val2)) = (values.next(), values.next()) {
-        seeds.push((val1, val1 + val2));
+    let values = parse_values(input.trim_start_matches("seeds:"))?;
+    for pair in values.chunks_exact(2) {
+        let (val1, val2) = (pair[0], pair[1]);
+        // A range of `val2` seeds starting at `val1`, kept as inclusive bounds. Empty ranges hold no seeds.
+        let Some(last) = val2.checked_sub(1) else {
+            continue;
+        };
+        let end = val1.checked_add(last).ok_or_else(|| invalid_data(format!("seed range {} {} overflows", val1, val2)))?;
+        seeds.push((val1, end));
     }
-    seeds
+    Ok(seeds)
 }
 
 
-fn parse_section(section: &str) -> (String, RangeSet) {
+fn parse_section(section: &str) -> io::Result<(String, RangeSet)> {
     let mut section_name = String::new();
     let mut parsed_section = RangeSet::new();
     let mut lines = section.lines();
 
     if let Some(first_line) = lines.next() {
-        section_name = first_line.trim_end_matches(" map:").parse().unwrap();
+        section_name = String::from(first_line.trim_end_matches(" map:"));
     }
 
     for line in lines {
-        let mut values = line.split_whitespace();
-        if let (Some(dest_start), Some(source_start), Some(range_length)) = (values.next(), values.next(), values.next()) {
-            if let (Ok(dest_start), Ok(source_start), Ok(range_length)) = (dest_start.parse::<u64>(), source_start.parse::<u64>(), range_length.parse::<u64>()) {
+        let values = parse_values(line)?;
+        match values[..] {
+            [] => (),
+            [dest_start, source_start, range_length] => {
+                // Both ends of the line must exist, which rules out empty ranges and overflowing ones alike.
+                let fits = |start: u64| range_length.checked_sub(1).and_then(|last| start.checked_add(last)).is_some();
+                if !fits(source_start) || !fits(dest_start) {
+                    return Err(invalid_data(format!("map line \"{}\" is empty or overflows", line)));
+                }
                 parsed_section.insert(source_start, dest_start, range_length);
             }
+            _ => return Err(invalid_data(format!("expected three numbers in map line \"{}\"", line))),
         }
     }
 
-    (section_name, parsed_section)
+    Ok((section_name, parsed_section))
 }
 
-fn get_lowest_location_from_seeds(seeds: &Vec<(u64, u64)>, sections: &HashMap<String, RangeSet>) -> u64 {
-    let soil_range = get_new_ranges(seeds, sections.get("seed-to-soil").unwrap());
-    let fertilizer_range = get_new_ranges(&soil_range, sections.get("soil-to-fertilizer").unwrap());
-    let water_range = get_new_ranges(&fertilizer_range, sections.get("fertilizer-to-water").unwrap());
-    let light_range = get_new_ranges(&water_range, sections.get("water-to-light").unwrap());
-    let temperature_range = get_new_ranges(&light_range, sections.get("light-to-temperature").unwrap());
-    let humidity_range = get_new_ranges(&temperature_range, sections.get("temperature-to-humidity").unwrap());
-    let location_range = get_new_ranges(&humidity_range, sections.get("humidity-to-location").unwrap());
-    return get_lowest_from_ranges(&location_range);
+fn get_lowest_location_from_seeds(seeds: &[(u64, u64)], sections: &HashMap<String, RangeSet>, trace: &mut Trace, budget: &Budget) -> io::Result<u64> {
+    let mut ranges = seeds.to_vec();
+    for stage in STAGES {
+        ranges = trace.section(|| String::from(stage), |trace| get_new_ranges(&ranges, sections.get(stage).unwrap(), trace, budget))?;
+    }
+    let lowest = get_lowest_from_ranges(&ranges);
+    trace.note(|| format!("lowest location {}", lowest));
+    Ok(lowest)
 }
 
-fn get_lowest_from_ranges(ranges: &Vec<(u64, u64)>) -> u64 {
+fn get_lowest_from_ranges(ranges: &[(u64, u64)]) -> u64 {
     let mut curr_lowest = u64::MAX;
 
     for &(val, _) in ranges {
@@ -150,10 +322,152 @@ fn get_lowest_from_ranges(ranges: &Vec<(u64, u64)>) -> u64 {
 }
 
 
-fn get_new_ranges(curr_range: &Vec<(u64, u64)>, range_set: &RangeSet) -> Vec<(u64, u64)> {
+/// Where every interval goes through one map. Overlapping results are merged, otherwise
+/// intervals that overlap could split again at every stage and multiply.
+fn get_new_ranges(curr_range: &[(u64, u64)], range_set: &RangeSet, trace: &mut Trace, budget: &Budget) -> io::Result<Vec<(u64, u64)>> {
     let mut new_ranges = Vec::new();
     for (start, end) in curr_range {
-        new_ranges.extend(range_set.get(*start, *end));
+        new_ranges.extend(range_set.get(*start, *end, trace, budget)?);
+    }
+    new_ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(new_ranges.len());
+    for (start, end) in new_ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const EXAMPLE: &str = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+
+    #[test]
+    fn test_svg_export() {
+        let svg = to_svg(EXAMPLE).unwrap();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert_eq!(svg.matches("font-weight=\"bold\"").count(), 8);
+        // The lowest location and every interval it came from, one per column.
+        assert_eq!(svg.matches("fill=\"gold\"").count(), 8);
+        assert!(svg.contains("<title>[55, 67] line 2</title>"));
+    }
+
+    #[test]
+    fn test_example() {
+        let (part_1_seeds, part_2_seeds, sections) = parse_input(EXAMPLE).unwrap();
+        assert_eq!(get_lowest_location_from_seeds(&part_1_seeds, &sections, &mut Trace::disabled(), &Budget::unlimited()).unwrap(), 35);
+        assert_eq!(get_lowest_location_from_seeds(&part_2_seeds, &sections, &mut Trace::disabled(), &Budget::unlimited()).unwrap(), 46);
+        assert_eq!(part_2_seeds, vec![(79, 92), (55, 67)]);
+        assert_eq!(parse_part_2_seeds("seeds: 5 0 7 1").unwrap(), vec![(7, 7)]);
+    }
+
+    #[test]
+    fn test_malformed_almanac() {
+        assert!(parse_input("seeds: 1 x").is_err());
+        assert!(parse_input("seeds: 18446744073709551615 1").is_err());
+        assert!(parse_input(&EXAMPLE.replace("50 98 2", "50 98 0")).is_err());
+        assert!(parse_input(&EXAMPLE.replace("50 98 2", "18446744073709551615 98 2")).is_err());
+        assert!(parse_input(&EXAMPLE.replace("50 98 2", "50 98")).is_err());
+        assert!(parse_input(&EXAMPLE.replace("water-to-light map:", "water-to-lamp map:")).is_err());
+    }
+
+    #[test]
+    fn test_fragments_up_to_the_largest_value() {
+        let mut range_set = RangeSet::new();
+        range_set.insert(u64::MAX - 1, 0, 2);
+        range_set.insert(10, 100, 5);
+        let fragments: Vec<((u64, u64), (u64, u64))> = range_set.get_fragments(12, u64::MAX, &Budget::unlimited()).unwrap().iter()
+            .map(|fragment| (fragment.source, fragment.dest))
+            .collect();
+        assert_eq!(fragments, vec![((12, 14), (102, 104)), ((15, u64::MAX - 2), (15, u64::MAX - 2)), ((u64::MAX - 1, u64::MAX), (0, 1))]);
+    }
+
+    #[test]
+    fn test_budget_stops_the_mapping() {
+        let err = solve(EXAMPLE, &mut Trace::disabled(), &Budget::unlimited().with_step_limit(20)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    /// Where `value` goes through one map, looking the lines up one by one like the puzzle describes.
+    fn map_point(range_set: &RangeSet, value: u64) -> u64 {
+        range_set.ranges.iter()
+            .find(|&&(source_start, _, range_length)| source_start <= value && value < source_start + range_length)
+            .map_or(value, |&(source_start, dest_start, _)| dest_start + (value - source_start))
+    }
+
+    fn arb_range_set() -> impl Strategy<Value = RangeSet> {
+        prop::collection::vec((0..200u64, 0..200u64, 1..60u64), 0..6).prop_map(|lines| {
+            let mut range_set = RangeSet::new();
+            for (source_start, dest_start, range_length) in lines {
+                range_set.insert(source_start, dest_start, range_length);
+            }
+            range_set
+        })
+    }
+
+    fn arb_seeds() -> impl Strategy<Value = SeedRanges> {
+        prop::collection::vec((0..250u64, 0..40u64).prop_map(|(start, length)| (start, start + length)), 1..5)
+    }
+
+    proptest! {
+        #[test]
+        fn test_fragments_match_point_mapping(range_set in arb_range_set(), (start, end) in (0..250u64, 0..80u64).prop_map(|(start, length)| (start, start + length))) {
+            let mut mapped: Vec<u64> = range_set.get_fragments(start, end, &Budget::unlimited()).unwrap().iter()
+                .flat_map(|fragment| fragment.dest.0..=fragment.dest.1)
+                .collect();
+            let mut expected: Vec<u64> = (start..=end).map(|value| map_point(&range_set, value)).collect();
+            mapped.sort_unstable();
+            expected.sort_unstable();
+            prop_assert_eq!(mapped, expected);
+        }
+
+        #[test]
+        fn test_lowest_location_matches_point_mapping(stages in prop::collection::vec(arb_range_set(), STAGES.len()), seeds in arb_seeds()) {
+            let sections: HashMap<String, RangeSet> = STAGES.iter().map(|stage| String::from(*stage)).zip(stages.iter().cloned()).collect();
+            let expected = seeds.iter()
+                .flat_map(|&(start, end)| start..=end)
+                .map(|seed| stages.iter().fold(seed, |value, range_set| map_point(range_set, value)))
+                .min()
+                .unwrap();
+            prop_assert_eq!(get_lowest_location_from_seeds(&seeds, &sections, &mut Trace::disabled(), &Budget::unlimited()).unwrap(), expected);
+        }
     }
-    new_ranges
 }
@@ -1,3 +1,10 @@
+use std::io;
+
+use crate::answers::Answers;
+use crate::budget::{Budget, Interrupted};
+use crate::explain::Trace;
+use crate::helper::invalid_data;
+
 #[derive(Debug, Clone)]
 struct Race {
     total_time: u64,
@@ -5,102 +12,211 @@ struct Race {
 }
 
 impl Race {
-    fn count_ways_to_surpass_record(&self) -> u64 {
+    fn count_ways_to_surpass_record(&self, budget: &Budget) -> Result<u64, Interrupted> {
         let mut count = 0;
 
         for prep_time in 1..self.total_time {
-            let mut curr: i64 = (self.total_time * prep_time) as i64;
-            curr -= prep_time.pow(2) as i64;
-            curr -= self.record_distance as i64;
+            budget.step()?;
+            let mut curr: i128 = self.total_time as i128 * prep_time as i128;
+            curr -= (prep_time as i128).pow(2);
+            curr -= self.record_distance as i128;
             if curr > 0 {
                 count += 1;
             }
         }
-        count
+        Ok(count)
+    }
+
+    /// The same count without the loop. Holding for `t` ms travels `t * (total_time - t)` mm,
+    /// a parabola symmetric around `total_time / 2`, so only its first winning `t` is needed.
+    fn count_ways_closed_form(&self, budget: &Budget) -> Result<u64, Interrupted> {
+        budget.step()?;
+        let beats = |prep_time: u64| prep_time as u128 * (self.total_time - prep_time) as u128 > self.record_distance as u128;
+        let half = self.total_time / 2;
+        if !beats(half) {
+            return Ok(0);
+        }
+        let discriminant = ((self.total_time as u128).pow(2) - 4 * self.record_distance as u128) as f64;
+        let mut first = ((self.total_time as f64 - discriminant.sqrt()) / 2.0).max(0.0) as u64;
+        // The float root may be off by one either way for large races.
+        first = first.min(half);
+        while !beats(first) {
+            first += 1;
+        }
+        while first > 0 && beats(first - 1) {
+            first -= 1;
+        }
+        Ok(self.total_time - 2 * first + 1)
     }
 }
 
 
-pub fn solve(input: &str) {
+/// Solves with the closed form, the loop takes as many steps as part two's race has milliseconds.
+pub fn solve(input: &str, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
     println!("Solving Day 6 problems...");
-    let part_one_races = get_part_one_races(&input);
-    let part_two_races = get_part_two_race(&input);
-
-
-    let part_one_answer = solve_part_one(&part_one_races);
-    let part_two_answer = solve_part_two(&part_two_races);
-
+    let (part_one_answer, part_two_answer) = answers_fast(input, trace, budget)?;
     println!("The answer to the first gold star for day 6 is: {}", part_one_answer);
     println!("The answer to the second gold star for day 6 is: {}", part_two_answer);
+    Ok(Answers::new(part_one_answer, part_two_answer))
 }
 
+/// Both answers, counting every way to hold the button one by one.
+pub fn answers_naive(input: &str, trace: &mut Trace, budget: &Budget) -> io::Result<(u64, u64)> {
+    answers_with(input, trace, budget, Race::count_ways_to_surpass_record)
+}
 
-fn get_part_one_races(input: &str) -> Vec<Race> {
-    let mut races = Vec::new();
-
-    let lines: Vec<&str> = input.trim().split('\n').collect();
-
-    let times: Vec<u64> = lines[0]
-        .trim()
-        .split_whitespace()
-        .skip(1)
-        .map(|s| s.parse().unwrap())
-        .collect();
-    let distances: Vec<u64> = lines[1]
-        .trim()
-        .split_whitespace()
-        .skip(1)
-        .map(|s| s.parse().unwrap())
-        .collect();
-
-    for (time, distance) in times.into_iter().zip(distances.into_iter()) {
-        races.push(Race {
-            total_time: time,
-            record_distance: distance,
-        });
-    }
+/// Both answers, counting the ways to hold the button in closed form.
+pub fn answers_fast(input: &str, trace: &mut Trace, budget: &Budget) -> io::Result<(u64, u64)> {
+    answers_with(input, trace, budget, Race::count_ways_closed_form)
+}
+
+/// How a race's ways to win are counted, giving up once `budget` is spent.
+type CountWays = fn(&Race, &Budget) -> Result<u64, Interrupted>;
+
+fn answers_with(input: &str, trace: &mut Trace, budget: &Budget, count_ways: CountWays) -> io::Result<(u64, u64)> {
+    let part_one_races = get_part_one_races(input)?;
+    let part_two_races = get_part_two_race(input)?;
 
-    races
+    let part_one_answer = trace.time_part(1, |trace| trace.section(|| String::from("part one"), |trace| solve_part_one(&part_one_races, count_ways, trace, budget)))?
+        .ok_or_else(|| invalid_data(String::from("the product of the ways overflows")))?;
+    let part_two_answer = trace.time_part(2, |trace| trace.section(|| String::from("part two"), |trace| solve_part_two(&part_two_races, count_ways, trace, budget)))?;
+    Ok((part_one_answer, part_two_answer))
 }
 
-fn get_part_two_race(input: &str) -> Race {
-    let lines: Vec<&str> = input.trim().split('\n').collect();
-
-    let time: u64 = lines[0]
-        .trim()
-        .split_whitespace()
-        .skip(1)
-        .map(|s| s.parse::<u64>().unwrap().to_string())
-        .collect::<String>()
-        .parse()
-        .unwrap();
-
-    let distance: u64 = lines[1]
-        .trim()
-        .split_whitespace()
-        .skip(1)
-        .map(|s| s.parse::<u64>().unwrap().to_string())
-        .collect::<String>()
-        .parse()
-        .unwrap();
-
-
-    Race {
-        record_distance: distance,
-        total_time: time,
+/// The numbers after `Time:` and after `Distance:`, as written.
+fn get_rows(input: &str) -> io::Result<(Vec<&str>, Vec<&str>)> {
+    let mut lines = input.trim().lines();
+    let mut row = |label: &str| {
+        let line = lines.next().unwrap_or("");
+        let numbers = line.strip_prefix(label)
+            .ok_or_else(|| invalid_data(format!("expected a line starting with \"{}\", found \"{}\"", label, line)))?;
+        let numbers: Vec<&str> = numbers.split_whitespace().collect();
+        match numbers.iter().find(|number| !number.bytes().all(|b| b.is_ascii_digit())) {
+            Some(number) => Err(invalid_data(format!("invalid number \"{}\"", number))),
+            None => Ok(numbers),
+        }
+    };
+    let times = row("Time:")?;
+    let distances = row("Distance:")?;
+    if times.len() != distances.len() {
+        return Err(invalid_data(format!("{} times but {} distances", times.len(), distances.len())));
     }
+    Ok((times, distances))
 }
 
+fn parse_number(number: &str) -> io::Result<u64> {
+    number.parse().map_err(|_| invalid_data(format!("\"{}\" does not fit in 64 bits", number)))
+}
 
-fn solve_part_one(races: &Vec<Race>) -> u64 {
-    let mut answer = 1;
+fn get_part_one_races(input: &str) -> io::Result<Vec<Race>> {
+    let (times, distances) = get_rows(input)?;
+    times.into_iter().zip(distances)
+        .map(|(time, distance)| Ok(Race { total_time: parse_number(time)?, record_distance: parse_number(distance)? }))
+        .collect()
+}
+
+/// The races read as one, ignoring the spaces between their numbers.
+fn get_part_two_race(input: &str) -> io::Result<Race> {
+    let (times, distances) = get_rows(input)?;
+    Ok(Race {
+        record_distance: parse_number(&distances.concat())?,
+        total_time: parse_number(&times.concat())?,
+    })
+}
+
+
+/// The product of every race's ways, `None` if it does not fit in a `u64`.
+fn solve_part_one(races: &[Race], count_ways: CountWays, trace: &mut Trace, budget: &Budget) -> Result<Option<u64>, Interrupted> {
+    let mut answer: u64 = 1;
     for race in races.iter() {
-        answer *= race.count_ways_to_surpass_record();
+        let ways = count_ways_with_trace(race, count_ways, trace, budget)?;
+        let Some(product) = answer.checked_mul(ways) else {
+            return Ok(None);
+        };
+        answer = product;
     }
-    answer
+    Ok(Some(answer))
+}
+
+
+fn solve_part_two(race: &Race, count_ways: CountWays, trace: &mut Trace, budget: &Budget) -> Result<u64, Interrupted> {
+    count_ways_with_trace(race, count_ways, trace, budget)
+}
+
+fn count_ways_with_trace(race: &Race, count_ways: CountWays, trace: &mut Trace, budget: &Budget) -> Result<u64, Interrupted> {
+    let ways = count_ways(race, budget)?;
+    trace.note(|| format!("{} ms to beat {} mm: {} ways", race.total_time, race.record_distance, ways));
+    Ok(ways)
 }
 
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const EXAMPLE: &str = "Time:      7  15   30
+Distance:  9  40  200";
+
+    #[test]
+    fn test_example() {
+        assert_eq!(answers_naive(EXAMPLE, &mut Trace::disabled(), &Budget::unlimited()).unwrap(), (288, 71503));
+        assert_eq!(answers_fast(EXAMPLE, &mut Trace::disabled(), &Budget::unlimited()).unwrap(), (288, 71503));
+    }
+
+    #[test]
+    fn test_naive_gives_up_on_long_races() {
+        let budget = Budget::unlimited().with_step_limit(1000);
+        let err = answers_naive(EXAMPLE, &mut Trace::disabled(), &budget).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(answers_fast(EXAMPLE, &mut Trace::disabled(), &Budget::unlimited().with_step_limit(1000)).is_ok());
+    }
+
+    #[test]
+    fn test_malformed_races() {
+        assert!(answers_fast("Time: 7 15\nDistance: 9", &mut Trace::disabled(), &Budget::unlimited()).is_err());
+        assert!(answers_fast("Time: 7\n", &mut Trace::disabled(), &Budget::unlimited()).is_err());
+        assert!(answers_fast("Time: 7 x\nDistance: 9 1", &mut Trace::disabled(), &Budget::unlimited()).is_err());
+        assert!(answers_fast("Time: 99999999999999999999\nDistance: 1", &mut Trace::disabled(), &Budget::unlimited()).is_err());
+        let race = Race { total_time: u64::MAX, record_distance: 0 };
+        assert_eq!(race.count_ways_closed_form(&Budget::unlimited()), Ok(u64::MAX - 1));
+    }
 
-fn solve_part_two(race: &Race) -> u64 {
-    race.count_ways_to_surpass_record()
-}
\ No newline at end of file
+    #[test]
+    fn test_closed_form_matches_loop() {
+        for total_time in 0..60 {
+            for record_distance in 0..total_time * total_time / 4 + 2 {
+                let race = Race { total_time, record_distance };
+                let budget = Budget::unlimited();
+                assert_eq!(race.count_ways_closed_form(&budget), race.count_ways_to_surpass_record(&budget), "{:?}", race);
+            }
+        }
+    }
+
+    /// Races the boat for every possible hold, the way the puzzle describes it.
+    fn simulate(race: &Race) -> u64 {
+        (0..=race.total_time)
+            .filter(|&hold| {
+                let distance = (0..race.total_time - hold).fold(0, |travelled, _| travelled + hold);
+                distance > race.record_distance
+            })
+            .count() as u64
+    }
+
+    fn arb_race() -> impl Strategy<Value = Race> {
+        (0..400u64).prop_flat_map(|total_time| {
+            (Just(total_time), 0..total_time * total_time / 4 + 10)
+                .prop_map(|(total_time, record_distance)| Race { total_time, record_distance })
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_counts_match_simulation(race in arb_race()) {
+            let budget = Budget::unlimited();
+            let expected = simulate(&race);
+            prop_assert_eq!(race.count_ways_to_surpass_record(&budget), Ok(expected));
+            prop_assert_eq!(race.count_ways_closed_form(&budget), Ok(expected));
+        }
+    }
+}
@@ -1,21 +1,41 @@
 use std::cmp::Ordering;
+use std::io::{self, BufRead, Write};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum Kind {
-    FIVE = 1,
-    FOUR = 2,
-    FULL = 3,
-    THREE = 4,
-    TWO = 5,
-    ONE = 6,
-    HIGH = 7,
+use crate::answers::Answers;
+use crate::budget::{Budget, Interrupted};
+use crate::explain::Trace;
+
+pub mod stats;
+
+/// Cards from weakest to strongest, as ranked without jokers.
+const CARDS: &str = "23456789TJQKA";
+
+/// Hand types, declared from weakest to strongest so the derived `Ord` ranks them directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    High,
+    One,
+    Two,
+    Three,
+    Full,
+    Four,
+    Five,
 }
 
-#[derive(Debug, Eq)]
-struct CamelCard {
-    label: String,
-    label_strength: Vec<u64>,
+/// A hand compared by strength only: first by `kind`, then card by card.
+///
+/// Field order matters, the derived comparisons are lexicographic over `kind`,
+/// `label_strength` and finally `label`, which keeps `Ord` consistent with `Eq`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Hand {
     kind: Kind,
+    label_strength: Vec<u64>,
+    label: String,
+}
+
+#[derive(Debug)]
+struct CamelCard {
+    hand: Hand,
     bid: u64,
 }
 
@@ -26,8 +46,22 @@ struct CamelCards {
 }
 
 
+/// A hand whose type is raised by playing its jokers as another card.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JokerUpgrade {
+    pub label: String,
+    pub resolved: String,
+    pub before: Kind,
+    pub after: Kind,
+}
+
 impl Kind {
-    fn get_kind_from_label(label: &str, is_part_two: bool) -> Self {
+    /// The type of a hand, with `J` played as a joker when `is_part_two` is set.
+    pub fn get_kind_from_label(label: &str, is_part_two: bool) -> Self {
+        if is_part_two {
+            return Kind::get_kind_from_label(&resolve_jokers(label), false);
+        }
+
         let mut counts = [0; 13];
         for c in label.chars() {
             match c {
@@ -48,70 +82,98 @@ impl Kind {
             }
         }
 
-        if is_part_two  && counts[10] > 0{
-            let joker_count = counts[10];
-            counts[10] = 0;
-            let max_count_index = counts.iter().enumerate().max_by_key(|&(_, &count)| count).map(|(i, _)| i);
-            if let Some(index) = max_count_index {
-                counts[index] += joker_count;
-            }
-        }
-
         let unique_count = counts.iter().filter(|&x| *x > 0).count();
 
         match unique_count {
-            1 => Kind::FIVE,
+            1 => Kind::Five,
             2 => {
-                if counts.iter().any(|&x| x == 4) {
-                    Kind::FOUR
+                if counts.contains(&4) {
+                    Kind::Four
                 } else {
-                    Kind::FULL
+                    Kind::Full
                 }
             }
             3 => {
-                if counts.iter().any(|&x| x == 3) {
-                    Kind::THREE
+                if counts.contains(&3) {
+                    Kind::Three
                 } else {
-                    Kind::TWO
+                    Kind::Two
                 }
             }
-            4 => Kind::ONE,
-            _ => Kind::HIGH,
+            4 => Kind::One,
+            _ => Kind::High,
         }
     }
 }
 
 
-impl CamelCard {
-    fn new(label: &str, bid: u64, is_part_two: bool) -> Self {
-        CamelCard {
-            label: String::from(label),
+/// The concrete hand a joker hand plays as: every joker becomes the most common other
+/// card, the strongest of them on a tie, so `KTJJT` plays as `KTTTT`. All jokers play as aces.
+pub fn resolve_jokers(label: &str) -> String {
+    let replacement = label.chars()
+        .filter(|&c| c != 'J')
+        .max_by_key(|&card| (label.chars().filter(|&c| c == card).count(), CARDS.find(card)))
+        .unwrap_or('A');
+    label.chars().map(|c| if c == 'J' { replacement } else { c }).collect()
+}
+
+/// Every hand in the input whose type changes once its jokers are played.
+pub fn get_joker_upgrades<R: BufRead>(input: R) -> io::Result<Vec<JokerUpgrade>> {
+    let mut upgrades = Vec::new();
+    for line in input.lines() {
+        let line = line?;
+        let Some(label) = line.split_whitespace().next() else {
+            continue;
+        };
+        let before = Kind::get_kind_from_label(label, false);
+        let after = Kind::get_kind_from_label(label, true);
+        if before != after {
+            upgrades.push(JokerUpgrade { label: String::from(label), resolved: resolve_jokers(label), before, after });
+        }
+    }
+    Ok(upgrades)
+}
+
+/// Prints the hands jokers upgraded and the concrete hand each one plays as.
+pub fn display<R: BufRead, W: Write>(input: R, out: &mut W) -> io::Result<()> {
+    let upgrades = get_joker_upgrades(input)?;
+    writeln!(out, "{} hands change type with jokers", upgrades.len())?;
+    for upgrade in upgrades {
+        writeln!(out, "{} {:?} -> {} {:?}", upgrade.label, upgrade.before, upgrade.resolved, upgrade.after)?;
+    }
+    Ok(())
+}
+
+
+impl Hand {
+    fn new(label: &str, is_part_two: bool) -> Self {
+        Hand {
+            kind: Kind::get_kind_from_label(label, is_part_two),
             label_strength: if is_part_two {
-                CamelCard::get_label_strength_part_two(label)
+                Hand::get_label_strength_part_two(label)
             } else {
-                CamelCard::get_label_strength_part_one(label)
+                Hand::get_label_strength_part_one(label)
             },
-            kind: Kind::get_kind_from_label(label, is_part_two),
-            bid,
+            label: String::from(label),
         }
     }
 
     fn get_label_strength_part_one(label: &str) -> Vec<u64> {
         label.chars()
             .map(|c| match c {
-                'A' => 1,
-                'K' => 2,
-                'Q' => 3,
-                'J' => 4,
-                'T' => 5,
-                '9' => 6,
+                '2' => 1,
+                '3' => 2,
+                '4' => 3,
+                '5' => 4,
+                '6' => 5,
+                '7' => 6,
                 '8' => 7,
-                '7' => 8,
-                '6' => 9,
-                '5' => 10,
-                '4' => 11,
-                '3' => 12,
-                '2' => 13,
+                '9' => 8,
+                'T' => 9,
+                'J' => 10,
+                'Q' => 11,
+                'K' => 12,
+                'A' => 13,
                 _ => 0,
             })
             .collect()
@@ -120,53 +182,38 @@ impl CamelCard {
     fn get_label_strength_part_two(label: &str) -> Vec<u64> {
         label.chars()
             .map(|c| match c {
-                'A' => 1,
-                'K' => 2,
-                'Q' => 3,
-                'T' => 4,
-                '9' => 5,
-                '8' => 6,
+                'J' => 1,
+                '2' => 2,
+                '3' => 3,
+                '4' => 4,
+                '5' => 5,
+                '6' => 6,
                 '7' => 7,
-                '6' => 8,
-                '5' => 9,
-                '4' => 10,
-                '3' => 11,
-                '2' => 12,
-                'J' => 13,
+                '8' => 8,
+                '9' => 9,
+                'T' => 10,
+                'Q' => 11,
+                'K' => 12,
+                'A' => 13,
                 _ => 0,
             })
             .collect()
     }
 }
 
-impl PartialEq<Self> for CamelCard {
-    fn eq(&self, other: &Self) -> bool {
-        self.kind == other.kind && self.bid == other.bid && self.label == other.label
-    }
-}
 
-impl PartialOrd<Self> for CamelCard {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match self.kind.cmp(&other.kind) {
-            Ordering::Less => { Some(Ordering::Less) }
-            Ordering::Equal => {
-                for (self_strength, other_strength) in self.label_strength.iter().zip(other.label_strength.iter()) {
-                    match self_strength.cmp(other_strength) {
-                        Ordering::Less => return Some(Ordering::Less),
-                        Ordering::Greater => return Some(Ordering::Greater),
-                        Ordering::Equal => continue,
-                    }
-                }
-                Some(self.bid.cmp(&other.bid))
-            }
-            Ordering::Greater => { Some(Ordering::Greater) }
+impl CamelCard {
+    fn new(label: &str, bid: u64, is_part_two: bool) -> Self {
+        CamelCard {
+            hand: Hand::new(label, is_part_two),
+            bid,
         }
     }
-}
 
-impl Ord for CamelCard {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+    /// Orders cards by hand strength. Identical hands are tied by strength, so they are
+    /// ordered by bid to keep the ranking independent of input order.
+    fn cmp_for_ranking(&self, other: &Self) -> Ordering {
+        self.hand.cmp(&other.hand).then_with(|| self.bid.cmp(&other.bid))
     }
 }
 
@@ -181,50 +228,62 @@ impl CamelCards {
     }
 
     fn sort(&mut self) {
-        self.camel_cards.sort();
+        self.camel_cards.sort_by(CamelCard::cmp_for_ranking);
     }
 }
 
-pub fn solve(input: &str) {
+pub fn solve<R: BufRead>(input: R, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
     println!("Solving Day 7 problems...");
-    let mut part_one_cards = parse_input(input, false);
-    let part_one_solution = get_ranks_bid_summation(&mut part_one_cards);
-
-    let mut part_two_cards = parse_input(input, true);
-    let part_two_solution = get_ranks_bid_summation(&mut part_two_cards);
+    let (mut part_one_cards, mut part_two_cards) = parse_input(input)?;
+    let overflow = || io::Error::new(io::ErrorKind::InvalidData, "the total winnings do not fit in 64 bits");
+    let part_one_solution = trace.time_part(1, |trace| trace.section(|| String::from("part one"), |trace| get_ranks_bid_summation(&mut part_one_cards, trace, budget)))?
+        .ok_or_else(overflow)?;
+    let part_two_solution = trace.time_part(2, |trace| trace.section(|| String::from("part two"), |trace| get_ranks_bid_summation(&mut part_two_cards, trace, budget)))?
+        .ok_or_else(overflow)?;
 
     println!("The answer to the first gold star for day 7 is: {}", part_one_solution);
     println!("The answer to the second gold star for day 7 is: {}", part_two_solution);
+    Ok(Answers::new(part_one_solution, part_two_solution))
 }
 
-fn parse_input(input: &str, is_part_two: bool) -> CamelCards {
-    let mut cards = CamelCards::new();
+/// Reads the hands once, building the part one and part two rankings side by side.
+fn parse_input<R: BufRead>(input: R) -> io::Result<(CamelCards, CamelCards)> {
+    let mut part_one_cards = CamelCards::new();
+    let mut part_two_cards = CamelCards::new();
 
     for line in input.lines() {
+        let line = line?;
         let mut parts = line.split_whitespace();
         if let (Some(label), Some(bid_str)) = (parts.next(), parts.next()) {
             if let Ok(bid) = bid_str.parse::<u64>() {
-                cards.insert(label, bid, is_part_two);
+                part_one_cards.insert(label, bid, false);
+                part_two_cards.insert(label, bid, true);
             }
         }
     }
 
-    cards
+    Ok((part_one_cards, part_two_cards))
 }
 
-fn get_ranks_bid_summation(camel_cards: &mut CamelCards) -> u64 {
+/// The total winnings, `None` if they do not fit in a `u64`.
+fn get_ranks_bid_summation(camel_cards: &mut CamelCards, trace: &mut Trace, budget: &Budget) -> Result<Option<u64>, Interrupted> {
     camel_cards.sort();
-    let mut answer = 0;
-    let mut rank = 1;
-    for card in camel_cards.camel_cards.iter().rev() {
-        answer += rank * card.bid;
-        rank += 1;
+    let mut answer: u64 = 0;
+    for (card, rank) in camel_cards.camel_cards.iter().zip(1u64..) {
+        budget.step()?;
+        trace.note(|| format!("{} {:?}: rank {}, wins {} * {}", card.hand.label, card.hand.kind, rank, rank, card.bid));
+        let Some(total) = rank.checked_mul(card.bid).and_then(|winnings| answer.checked_add(winnings)) else {
+            return Ok(None);
+        };
+        answer = total;
     }
-    answer
+    Ok(Some(answer))
 }
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
     #[test]
@@ -236,9 +295,146 @@ mod tests {
         camel_cards.insert("KTJJT", 220, true);
         camel_cards.insert("QQQJA", 483, true);
 
-        let result = get_ranks_bid_summation(&mut camel_cards);
-        assert_eq!(result, 5905);
+        let result = get_ranks_bid_summation(&mut camel_cards, &mut Trace::disabled(), &Budget::unlimited());
+        assert_eq!(result, Ok(Some(5905)));
 
         // You can add more assertions for other test cases here if needed
     }
+
+    #[test]
+    fn test_overflow() {
+        let input = format!("32T3K {}\nKK677 {}", u64::MAX, u64::MAX);
+        assert!(solve(input.as_bytes(), &mut Trace::disabled(), &Budget::unlimited()).is_err());
+    }
+
+    #[test]
+    fn test_identical_hands_are_ranked_by_bid() {
+        let mut camel_cards = CamelCards::new();
+        camel_cards.insert("KK677", 30, false);
+        camel_cards.insert("KK677", 10, false);
+        camel_cards.insert("32T3K", 5, false);
+
+        assert_eq!(get_ranks_bid_summation(&mut camel_cards, &mut Trace::disabled(), &Budget::unlimited()), Ok(Some(5 + 2 * 10 + 3 * 30)));
+    }
+
+    #[test]
+    fn test_resolve_jokers() {
+        assert_eq!(resolve_jokers("KTJJT"), "KTTTT");
+        assert_eq!(resolve_jokers("T55J5"), "T5555");
+        assert_eq!(resolve_jokers("QJJQ2"), "QQQQ2");
+        assert_eq!(resolve_jokers("2J3J4"), "24344");
+        assert_eq!(resolve_jokers("JJJJJ"), "AAAAA");
+        assert_eq!(resolve_jokers("32T3K"), "32T3K");
+    }
+
+    #[test]
+    fn test_joker_upgrades() {
+        let input = "32T3K 765\nT55J5 684\nKK677 28\nKTJJT 220\nQQQJA 483\n";
+        let upgrades = get_joker_upgrades(input.as_bytes()).unwrap();
+        let labels: Vec<_> = upgrades.iter().map(|upgrade| (upgrade.label.as_str(), upgrade.after)).collect();
+        assert_eq!(labels, vec![("T55J5", Kind::Four), ("KTJJT", Kind::Four), ("QQQJA", Kind::Four)]);
+    }
+
+    fn arb_label() -> impl Strategy<Value = String> {
+        let card = prop::sample::select(CARDS.chars().collect::<Vec<_>>());
+        prop::collection::vec(card, 5).prop_map(|cards| cards.into_iter().collect())
+    }
+
+    /// Every way of playing the jokers in `label`, each one standing for any card.
+    fn all_substitutions(label: &str) -> Vec<String> {
+        label.chars().fold(vec![String::new()], |prefixes, c| {
+            let options: Vec<char> = if c == 'J' { CARDS.chars().collect() } else { vec![c] };
+            prefixes.iter()
+                .flat_map(|prefix| options.iter().map(move |&option| format!("{}{}", prefix, option)))
+                .collect()
+        })
+    }
+
+    fn arb_hand() -> impl Strategy<Value = Hand> {
+        let card = prop::sample::select(vec!['2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K', 'A']);
+        (prop::collection::vec(card, 5), any::<bool>())
+            .prop_map(|(cards, is_part_two)| Hand::new(&cards.into_iter().collect::<String>(), is_part_two))
+    }
+
+    /// The type of a hand without jokers, straight from the sorted card counts.
+    fn naive_kind(label: &str) -> Kind {
+        let mut counts: Vec<usize> = CARDS.chars().map(|card| label.chars().filter(|&c| c == card).count()).filter(|&count| count > 0).collect();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        match counts[..] {
+            [5] => Kind::Five,
+            [4, 1] => Kind::Four,
+            [3, 2] => Kind::Full,
+            [3, 1, 1] => Kind::Three,
+            [2, 2, 1] => Kind::Two,
+            [2, 1, 1, 1] => Kind::One,
+            _ => Kind::High,
+        }
+    }
+
+    /// Compares two hands the way the puzzle describes it, trying every card a joker could stand for.
+    fn naive_cmp(a: &str, b: &str, is_part_two: bool) -> Ordering {
+        let kind = |label: &str| if is_part_two {
+            all_substitutions(label).iter().map(|substitution| naive_kind(substitution)).max().unwrap()
+        } else {
+            naive_kind(label)
+        };
+        let order = if is_part_two { "J23456789TQKA" } else { CARDS };
+        let strengths = |label: &str| label.chars().map(|c| order.find(c).unwrap()).collect::<Vec<_>>();
+        kind(a).cmp(&kind(b)).then_with(|| strengths(a).cmp(&strengths(b)))
+    }
+
+    proptest! {
+        #[test]
+        fn test_ranking_matches_naive_comparator(hands in prop::collection::vec((arb_label(), 1..1000u64), 0..30), is_part_two in any::<bool>()) {
+            let mut camel_cards = CamelCards::new();
+            for (label, bid) in &hands {
+                camel_cards.insert(label, *bid, is_part_two);
+            }
+            let winnings = get_ranks_bid_summation(&mut camel_cards, &mut Trace::disabled(), &Budget::unlimited()).unwrap();
+
+            let mut expected = hands.clone();
+            expected.sort_by(|(a, a_bid), (b, b_bid)| naive_cmp(a, b, is_part_two).then(a_bid.cmp(b_bid)));
+            let ranked: Vec<(String, u64)> = camel_cards.camel_cards.iter().map(|card| (card.hand.label.clone(), card.bid)).collect();
+            prop_assert_eq!(&ranked, &expected);
+            prop_assert_eq!(winnings, Some(expected.iter().zip(1..).map(|((_, bid), rank)| rank * bid).sum()));
+        }
+
+        #[test]
+        fn test_resolved_jokers_are_optimal(label in arb_label()) {
+            let best = all_substitutions(&label).iter()
+                .map(|substitution| Kind::get_kind_from_label(substitution, false))
+                .max()
+                .unwrap();
+            let resolved = resolve_jokers(&label);
+            prop_assert_eq!(Kind::get_kind_from_label(&resolved, false), best);
+            prop_assert_eq!(Kind::get_kind_from_label(&label, true), best);
+            prop_assert!(!resolved.contains('J'));
+        }
+
+        #[test]
+        fn test_ord_is_reflexive(a in arb_hand()) {
+            prop_assert_eq!(a.cmp(&a), Ordering::Equal);
+        }
+
+        #[test]
+        fn test_ord_is_antisymmetric(a in arb_hand(), b in arb_hand()) {
+            prop_assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+        }
+
+        #[test]
+        fn test_ord_is_transitive(a in arb_hand(), b in arb_hand(), c in arb_hand()) {
+            if a <= b && b <= c {
+                prop_assert!(a <= c);
+            }
+            if a >= b && b >= c {
+                prop_assert!(a >= c);
+            }
+        }
+
+        #[test]
+        fn test_ord_is_consistent_with_eq(a in arb_hand(), b in arb_hand()) {
+            prop_assert_eq!(a == b, a.cmp(&b) == Ordering::Equal);
+            prop_assert_eq!(a.partial_cmp(&b), Some(a.cmp(&b)));
+        }
+    }
 }
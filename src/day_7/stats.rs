@@ -0,0 +1,135 @@
+//! Camel Cards statistics over the whole universe of 13^5 possible hands.
+
+use std::io::{self, Write};
+
+use super::{Hand, Kind, CARDS};
+
+/// Every possible hand, 13 cards in each of 5 positions.
+pub const UNIVERSE_SIZE: u64 = 13u64.pow(5);
+
+/// Hand types from weakest to strongest, matching their position in `HandStats::kind_counts`.
+pub const KINDS: [Kind; 7] = [Kind::High, Kind::One, Kind::Two, Kind::Three, Kind::Full, Kind::Four, Kind::Five];
+
+/// How the hand types and strengths are distributed over every possible hand,
+/// with or without `J` played as a joker.
+#[derive(Debug, Clone)]
+pub struct HandStats {
+    is_part_two: bool,
+    kind_counts: [u64; 7],
+    /// The strength key of every hand, sorted ascending.
+    strengths: Vec<u64>,
+}
+
+impl HandStats {
+    /// Classifies all 13^5 hands.
+    pub fn enumerate(is_part_two: bool) -> Self {
+        let mut kind_counts = [0; 7];
+        let mut strengths = Vec::with_capacity(UNIVERSE_SIZE as usize);
+        for idx in 0..UNIVERSE_SIZE {
+            let hand = Hand::new(&get_label(idx), is_part_two);
+            kind_counts[hand.kind as usize] += 1;
+            strengths.push(get_strength_key(&hand));
+        }
+        strengths.sort_unstable();
+        HandStats { is_part_two, kind_counts, strengths }
+    }
+
+    /// How many hands are of type `kind`.
+    pub fn count(&self, kind: Kind) -> u64 {
+        self.kind_counts[kind as usize]
+    }
+
+    /// The chance a uniformly random hand is of type `kind`.
+    pub fn probability(&self, kind: Kind) -> f64 {
+        self.count(kind) as f64 / UNIVERSE_SIZE as f64
+    }
+
+    /// The share of all hands, in percent, that `label` beats. `None` if `label` is not five cards.
+    pub fn percentile(&self, label: &str) -> Option<f64> {
+        if label.chars().count() != 5 || !label.chars().all(|c| CARDS.contains(c)) {
+            return None;
+        }
+        let key = get_strength_key(&Hand::new(label, self.is_part_two));
+        let weaker = self.strengths.partition_point(|&strength| strength < key);
+        Some(weaker as f64 * 100.0 / UNIVERSE_SIZE as f64)
+    }
+}
+
+/// The `idx`-th hand of the universe, reading `idx` as five base 13 digits.
+fn get_label(idx: u64) -> String {
+    (0..5).rev()
+        .map(|position| CARDS.as_bytes()[(idx / 13u64.pow(position) % 13) as usize] as char)
+        .collect()
+}
+
+/// Packs a hand into a single number ordered the same way as `Hand`.
+fn get_strength_key(hand: &Hand) -> u64 {
+    hand.label_strength.iter()
+        .fold(hand.kind as u64, |key, &strength| key * 13 + strength.saturating_sub(1))
+}
+
+/// Prints the probability of each hand type with and without jokers, then the percentile of each of `hands`.
+pub fn report<W: Write>(hands: &[String], out: &mut W) -> io::Result<()> {
+    let without_jokers = HandStats::enumerate(false);
+    let with_jokers = HandStats::enumerate(true);
+    writeln!(out, "{:<6} {:>18} {:>18}", "kind", "without jokers", "with jokers")?;
+    for kind in KINDS.iter().rev() {
+        writeln!(out, "{:<6} {:>8} {:>8.4}% {:>8} {:>8.4}%", format!("{:?}", kind),
+                 without_jokers.count(*kind), without_jokers.probability(*kind) * 100.0,
+                 with_jokers.count(*kind), with_jokers.probability(*kind) * 100.0)?;
+    }
+    for hand in hands {
+        match (without_jokers.percentile(hand), with_jokers.percentile(hand)) {
+            (Some(without), Some(with)) => writeln!(out, "{} beats {:.4}% of hands without jokers, {:.4}% with jokers", hand, without, with)?,
+            _ => writeln!(out, "{} is not a hand of five cards", hand)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_without_jokers() {
+        let stats = HandStats::enumerate(false);
+        // 13 ranks for the main group, then choose the remaining distinct ranks and their positions.
+        assert_eq!(stats.count(Kind::Five), 13);
+        assert_eq!(stats.count(Kind::Four), 13 * 12 * 5);
+        assert_eq!(stats.count(Kind::Full), 13 * 12 * 10);
+        assert_eq!(stats.count(Kind::Three), 13 * 66 * 20);
+        assert_eq!(stats.count(Kind::Two), 78 * 11 * 30);
+        assert_eq!(stats.count(Kind::One), 13 * 220 * 60);
+        assert_eq!(stats.count(Kind::High), 13 * 12 * 11 * 10 * 9);
+    }
+
+    #[test]
+    fn test_jokers_only_raise_kinds() {
+        let without_jokers = HandStats::enumerate(false);
+        let with_jokers = HandStats::enumerate(true);
+        assert_eq!(KINDS.iter().map(|&kind| with_jokers.count(kind)).sum::<u64>(), UNIVERSE_SIZE);
+        // Jokers never lower a hand, so every tail of strongest kinds can only grow.
+        for split in 0..KINDS.len() {
+            let tail = |stats: &HandStats| KINDS[split..].iter().map(|&kind| stats.count(kind)).sum::<u64>();
+            assert!(tail(&with_jokers) >= tail(&without_jokers));
+        }
+        assert_eq!(with_jokers.count(Kind::High), 12 * 11 * 10 * 9 * 8);
+    }
+
+    #[test]
+    fn test_percentile() {
+        let stats = HandStats::enumerate(false);
+        assert_eq!(stats.percentile("23456"), Some(0.0));
+        assert_eq!(stats.percentile("AAAAA"), Some((UNIVERSE_SIZE - 1) as f64 * 100.0 / UNIVERSE_SIZE as f64));
+        assert!(stats.percentile("32T3K").unwrap() < stats.percentile("KK677").unwrap());
+        assert_eq!(stats.percentile("KTJJ"), None);
+        assert_eq!(stats.percentile("KTJJX"), None);
+
+        let with_jokers = HandStats::enumerate(true);
+        assert!(with_jokers.percentile("KTJJT").unwrap() > with_jokers.percentile("QQQJA").unwrap());
+        // Jokers are the weakest card, so five of them is the weakest five of a kind.
+        let weaker = UNIVERSE_SIZE - with_jokers.count(Kind::Five);
+        assert_eq!(with_jokers.percentile("JJJJJ"), Some(weaker as f64 * 100.0 / UNIVERSE_SIZE as f64));
+    }
+}
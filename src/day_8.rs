@@ -1,7 +1,13 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
 use std::rc::{Rc, Weak};
 
+use crate::answers::Answers;
+use crate::budget::{Budget, Interrupted};
+use crate::explain::Trace;
+
 #[derive(Debug)]
 struct Node {
     name: String,
@@ -19,58 +25,73 @@ impl Node {
     }
 }
 
+/// Why a walk through the network has no answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalkError {
+    MissingStart,
+    EmptySequence,
+    InvalidStep(char),
+    MissingChild { node: String, step: char },
+    /// The walk from `start` came back to where it stood after an earlier pass without reaching an end.
+    Unreachable { start: String },
+    Overflow,
+    Interrupted(Interrupted),
+}
+
+impl fmt::Display for WalkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalkError::MissingStart => write!(f, "Starting node AAA not found."),
+            WalkError::EmptySequence => write!(f, "The instructions are empty."),
+            WalkError::InvalidStep(step) => write!(f, "Invalid step in sequence: {}", step),
+            WalkError::MissingChild { node, step } => write!(f, "Node {} has no child for {}.", node, step),
+            WalkError::Unreachable { start } => write!(f, "No end node is reachable from {}.", start),
+            WalkError::Overflow => write!(f, "The step count overflows."),
+            WalkError::Interrupted(interrupted) => write!(f, "The walk was {}.", interrupted),
+        }
+    }
+}
+
+impl std::error::Error for WalkError {}
+
+impl From<Interrupted> for WalkError {
+    fn from(interrupted: Interrupted) -> Self {
+        WalkError::Interrupted(interrupted)
+    }
+}
 
-pub fn solve(input: &str) {
+pub fn solve(input: &str, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
     println!("Solving day 8 problems...");
 
     let sequence = input.lines().next().unwrap_or("").to_string();
     let nodes_mapping = get_node_mapping_from_input(input);
 
-    let part_one_solution = get_min_steps_to_end(&sequence, &nodes_mapping);
-
-    if part_one_solution != 0 {
-        println!("The solution to the first gold star is: {}", part_one_solution);
+    // The examples only hold one part each, so a part without an answer is reported and skipped.
+    // Running out of budget stops the whole day instead.
+    let mut answers = Answers::default();
+    match trace.time_part(1, |trace| trace.section(|| String::from("part one"), |trace| get_min_steps_to_end(&sequence, &nodes_mapping, trace, budget))) {
+        Ok(part_one_solution) => {
+            println!("The solution to the first gold star is: {}", part_one_solution);
+            answers.part_one = Some(part_one_solution.to_string());
+        }
+        Err(WalkError::Interrupted(interrupted)) => return Err(interrupted.into()),
+        Err(err) => println!("{}", err),
     }
 
-    let part_two_solution = get_min_steps_to_end_from_vector(&sequence, &nodes_mapping);
-
-    if part_two_solution != 0 {
-        println!("The solution to the second gold star is: {}", part_two_solution);
+    match trace.time_part(2, |trace| trace.section(|| String::from("part two"), |trace| get_min_steps_to_end_from_vector(&sequence, &nodes_mapping, trace, budget))) {
+        Ok(part_two_solution) => {
+            println!("The solution to the second gold star is: {}", part_two_solution);
+            answers.part_two = Some(part_two_solution.to_string());
+        }
+        Err(WalkError::Interrupted(interrupted)) => return Err(interrupted.into()),
+        Err(err) => println!("{}", err),
     }
+    Ok(answers)
 }
 
-fn get_min_steps_to_end(sequence: &str, nodes_mapping: &HashMap<String, Rc<RefCell<Node>>>) -> u64 {
-    if let Some(start_node) = nodes_mapping.get("AAA") {
-        let mut current_node = start_node.clone();
-        let mut steps = 0;
-        loop {
-            for step in sequence.chars() {
-                steps += 1;
-                let next_node = match step {
-                    'L' => current_node.borrow().left.clone(),
-                    'R' => current_node.borrow().right.clone(),
-                    _ => None,
-                };
-                if let Some(weak_node) = next_node {
-                    if let Some(strong_node) = weak_node.upgrade() {
-                        current_node = strong_node;
-                    } else {
-                        println!("Broken reference encountered.");
-                        return 0;
-                    }
-                } else {
-                    println!("Invalid step in sequence: {}", step);
-                    return 0;
-                }
-            }
-            if current_node.borrow().name == "ZZZ" {
-                return steps;
-            }
-        }
-    } else {
-        println!("Starting node AAA not found.");
-        return 0;
-    }
+fn get_min_steps_to_end(sequence: &str, nodes_mapping: &HashMap<String, Rc<RefCell<Node>>>, trace: &mut Trace, budget: &Budget) -> Result<u64, WalkError> {
+    let start_node = nodes_mapping.get("AAA").ok_or(WalkError::MissingStart)?;
+    get_path_len(start_node, sequence, |name| name == "ZZZ", trace, budget)
 }
 
 
@@ -95,7 +116,7 @@ fn get_node_mapping_from_input(input: &str) -> HashMap<String, Rc<RefCell<Node>>
             let children: Vec<&str> = children_part.split(',').map(|s| s.trim()).collect();
 
             if let Some(node) = nodes.get(name) {
-                let left_child = children.get(0).and_then(|&child| nodes.get(child)).cloned();
+                let left_child = children.first().and_then(|&child| nodes.get(child)).cloned();
                 let right_child = children.get(1).and_then(|&child| nodes.get(child)).cloned();
 
                 if let Some(left) = left_child {
@@ -111,20 +132,145 @@ fn get_node_mapping_from_input(input: &str) -> HashMap<String, Rc<RefCell<Node>>
 }
 
 
-fn get_min_steps_to_end_from_vector(sequence: &str, node_mapping: &HashMap<String, Rc<RefCell<Node>>>) -> u64 {
+/// The desert network as a Graphviz digraph. Edges are labelled with the instruction
+/// taking them, start and end nodes are filled in, and every strongly connected
+/// component with a cycle is drawn as its own cluster.
+pub fn to_dot(input: &str) -> String {
+    let nodes_mapping = get_node_mapping_from_input(input);
+    let mut names: Vec<&String> = nodes_mapping.keys().collect();
+    names.sort();
+    let edges: Vec<(String, String, &str)> = names.iter()
+        .flat_map(|&name| {
+            let (left, right) = get_children_names(&nodes_mapping[name]);
+            match (left, right) {
+                (Some(left), Some(right)) if left == right => vec![(name.clone(), left, "L/R")],
+                (left, right) => left.map(|left| (name.clone(), left, "L")).into_iter()
+                    .chain(right.map(|right| (name.clone(), right, "R")))
+                    .collect(),
+            }
+        })
+        .collect();
+
+    let mut dot = String::from("digraph desert {\n");
+    for (idx, component) in get_cyclic_components(&names, &edges).iter().enumerate() {
+        dot.push_str(&format!("    subgraph cluster_{} {{\n        label=\"component {}\";\n", idx, idx));
+        for name in component {
+            dot.push_str(&format!("        {};\n", dot_id(name)));
+        }
+        dot.push_str("    }\n");
+    }
+    for name in &names {
+        let style = match name.as_str() {
+            "AAA" => " [style=filled, fillcolor=green, penwidth=3]",
+            "ZZZ" => " [style=filled, fillcolor=red, penwidth=3]",
+            name if name.ends_with('A') => " [style=filled, fillcolor=palegreen]",
+            name if name.ends_with('Z') => " [style=filled, fillcolor=salmon]",
+            _ => "",
+        };
+        dot.push_str(&format!("    {}{};\n", dot_id(name), style));
+    }
+    for (from, to, label) in &edges {
+        dot.push_str(&format!("    {} -> {} [label={}];\n", dot_id(from), dot_id(to), dot_id(label)));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// `name` as a quoted DOT ID, whatever characters it holds.
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn get_children_names(node: &Rc<RefCell<Node>>) -> (Option<String>, Option<String>) {
+    let node = node.borrow();
+    let name_of = |child: &Option<Weak<RefCell<Node>>>| {
+        child.as_ref().and_then(Weak::upgrade).map(|child| child.borrow().name.clone())
+    };
+    (name_of(&node.left), name_of(&node.right))
+}
+
+/// Tarjan's algorithm, kept iterative so long chains cannot overflow the stack.
+/// Only components that contain a cycle are returned, singletons without a self loop are not interesting.
+fn get_cyclic_components(names: &[&String], edges: &[(String, String, &str)]) -> Vec<Vec<String>> {
+    let index_of: HashMap<&str, usize> = names.iter().enumerate().map(|(idx, name)| (name.as_str(), idx)).collect();
+    let mut adjacency = vec![Vec::new(); names.len()];
+    for (from, to, _) in edges {
+        adjacency[index_of[from.as_str()]].push(index_of[to.as_str()]);
+    }
+
+    let mut order = vec![usize::MAX; names.len()];
+    let mut low_link = vec![0; names.len()];
+    let mut on_stack = vec![false; names.len()];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut next_order = 0;
+
+    for root in 0..names.len() {
+        if order[root] != usize::MAX {
+            continue;
+        }
+        // Each frame is a node and how many of its edges have been followed so far.
+        let mut call_stack = vec![(root, 0)];
+        order[root] = next_order;
+        low_link[root] = next_order;
+        next_order += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&mut (node, ref mut edge_idx)) = call_stack.last_mut() {
+            if let Some(&next) = adjacency[node].get(*edge_idx) {
+                *edge_idx += 1;
+                if order[next] == usize::MAX {
+                    order[next] = next_order;
+                    low_link[next] = next_order;
+                    next_order += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    call_stack.push((next, 0));
+                } else if on_stack[next] {
+                    low_link[node] = low_link[node].min(order[next]);
+                }
+                continue;
+            }
+
+            call_stack.pop();
+            if let Some(&(parent, _)) = call_stack.last() {
+                low_link[parent] = low_link[parent].min(low_link[node]);
+            }
+            if low_link[node] == order[node] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                let is_cyclic = component.len() > 1 || adjacency[node].contains(&node);
+                if is_cyclic {
+                    let mut component: Vec<String> = component.into_iter().map(|member| names[member].clone()).collect();
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+    }
+    components
+}
+
+fn get_min_steps_to_end_from_vector(sequence: &str, node_mapping: &HashMap<String, Rc<RefCell<Node>>>, trace: &mut Trace, budget: &Budget) -> Result<u64, WalkError> {
     let initial_vec = get_initial_vec(node_mapping);
     let mut path_lengths = Vec::new();
 
     for node in initial_vec.iter() {
-        let length = get_path_len(node, sequence);
-        if length > 0 {
-            path_lengths.push(length);
-        }
+        let length = trace.section(|| format!("ghost from {}", node.borrow().name), |trace| get_path_len(node, sequence, |name| name.ends_with('Z'), trace, budget))?;
+        path_lengths.push(length);
     }
 
-    lcm_of_list(&path_lengths)
+    lcm_of_list(&path_lengths).ok_or(WalkError::Overflow)
 }
 
+/// Every ghost's starting node, sorted by name so runs walk them in the same order.
 fn get_initial_vec(node_mapping: &HashMap<String, Rc<RefCell<Node>>>) -> Vec<Rc<RefCell<Node>>> {
     let mut vec = Vec::new();
     for node in node_mapping.values() {
@@ -133,34 +279,42 @@ fn get_initial_vec(node_mapping: &HashMap<String, Rc<RefCell<Node>>>) -> Vec<Rc<
             vec.push(node.clone());
         }
     }
+    vec.sort_by(|a, b| a.borrow().name.cmp(&b.borrow().name));
     vec
 }
 
-fn get_path_len(start_node: &Rc<RefCell<Node>>, sequence: &str) -> u64 {
+/// Steps from `start_node` to the first node passing `is_end`, only checked after whole passes of `sequence`.
+///
+/// The walk is fully determined by where a pass starts, so standing on a node a second
+/// time at the end of a pass means it is going round in circles and will never finish.
+fn get_path_len(start_node: &Rc<RefCell<Node>>, sequence: &str, is_end: impl Fn(&str) -> bool, trace: &mut Trace, budget: &Budget) -> Result<u64, WalkError> {
+    if sequence.is_empty() {
+        return Err(WalkError::EmptySequence);
+    }
     let mut current_node = start_node.clone();
-    let mut steps = 0;
+    let mut steps: u64 = 0;
+    let mut pass_ends = HashSet::new();
     loop {
         for step in sequence.chars() {
-            steps += 1;
+            budget.step()?;
+            steps = steps.checked_add(1).ok_or(WalkError::Overflow)?;
             let next_node = match step {
                 'L' => current_node.borrow().left.clone(),
                 'R' => current_node.borrow().right.clone(),
-                _ => None,
+                _ => return Err(WalkError::InvalidStep(step)),
             };
-            if let Some(weak_node) = next_node {
-                if let Some(strong_node) = weak_node.upgrade() {
-                    current_node = strong_node;
-                } else {
-                    println!("Broken reference encountered.");
-                    return 0;
-                }
-            } else {
-                println!("Invalid step in sequence: {}", step);
-                return 0;
-            }
+            let Some(strong_node) = next_node.as_ref().and_then(Weak::upgrade) else {
+                return Err(WalkError::MissingChild { node: current_node.borrow().name.clone(), step });
+            };
+            current_node = strong_node;
+        }
+        let name = current_node.borrow().name.clone();
+        trace.note(|| format!("{} after {} steps", name, steps));
+        if is_end(&name) {
+            return Ok(steps);
         }
-        if current_node.borrow().name.ends_with('Z') {
-            return steps;
+        if !pass_ends.insert(name) {
+            return Err(WalkError::Unreachable { start: start_node.borrow().name.clone() });
         }
     }
 }
@@ -173,24 +327,79 @@ fn gcd(a: u64, b: u64) -> u64 {
     }
 }
 
-fn lcm(a: u64, b: u64) -> u64 {
-    a * b / gcd(a, b)
+fn lcm(a: u64, b: u64) -> Option<u64> {
+    (a / gcd(a, b)).checked_mul(b)
 }
 
-fn lcm_of_list(numbers: &[u64]) -> u64 {
-    numbers.iter().fold(1, |acc, &x| lcm(acc, x))
+fn lcm_of_list(numbers: &[u64]) -> Option<u64> {
+    numbers.iter().try_fold(1, |acc, &x| lcm(acc, x))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io;
+
+    use crate::budget::{Budget, Interrupted};
+    use crate::day_8::solve;
+    use crate::explain::Trace;
+
+    #[test]
+    fn test_dot_export() {
+        let input = r#"LLR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+ZZZ = (ZZZ, ZZZ)
+"#;
+        let dot = crate::day_8::to_dot(input);
+        assert!(dot.starts_with("digraph desert {"));
+        assert!(dot.contains("\"AAA\" -> \"BBB\" [label=\"L/R\"];"));
+        assert!(dot.contains("\"BBB\" -> \"ZZZ\" [label=\"R\"];"));
+        assert!(dot.contains("\"AAA\" [style=filled, fillcolor=green, penwidth=3];"));
+        assert!(dot.contains("subgraph cluster_0 {\n        label=\"component 0\";\n        \"ZZZ\";\n    }"));
+        assert!(dot.contains("subgraph cluster_1 {\n        label=\"component 1\";\n        \"AAA\";\n        \"BBB\";\n    }"));
+
+        let dot = crate::day_8::to_dot("L\n\na\"b = (c\\d, a\"b)\nc\\d = (c\\d, c\\d)\n");
+        assert!(dot.contains(r#"    "a\"b" -> "c\\d" [label="L"];"#), "{}", dot);
+    }
+
     #[test]
     fn test_example() {
-        let input = r#"
+        let input = r#"LR
 
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)
 "#;
         let sequence = "LR";
         let nodes_mapping = crate::day_8::get_node_mapping_from_input(input);
-        let result = crate::day_8::get_min_steps_to_end_from_vector(sequence, &nodes_mapping);
-        assert_eq!(result, 6);
+        let result = crate::day_8::get_min_steps_to_end_from_vector(sequence, &nodes_mapping, &mut Trace::disabled(), &Budget::unlimited());
+        assert_eq!(result, Ok(6));
+    }
+
+    #[test]
+    fn test_unreachable_end_terminates() {
+        let input = "LR\n\nAAA = (BBB, BBB)\nBBB = (AAA, AAA)\nZZZ = (ZZZ, ZZZ)\n";
+        let nodes_mapping = crate::day_8::get_node_mapping_from_input(input);
+        let result = crate::day_8::get_min_steps_to_end("LR", &nodes_mapping, &mut Trace::disabled(), &Budget::unlimited());
+        assert_eq!(result, Err(crate::day_8::WalkError::Unreachable { start: String::from("AAA") }));
+        let result = crate::day_8::get_min_steps_to_end("", &nodes_mapping, &mut Trace::disabled(), &Budget::unlimited());
+        assert_eq!(result, Err(crate::day_8::WalkError::EmptySequence));
+    }
+
+    #[test]
+    fn test_budget_stops_the_walk() {
+        let input = "LLR\n\nAAA = (BBB, BBB)\nBBB = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)\n";
+        let nodes_mapping = crate::day_8::get_node_mapping_from_input(input);
+        let budget = Budget::unlimited().with_step_limit(4);
+        let result = crate::day_8::get_min_steps_to_end("LLR", &nodes_mapping, &mut Trace::disabled(), &budget);
+        assert_eq!(result, Err(crate::day_8::WalkError::Interrupted(Interrupted::OutOfSteps { limit: 4 })));
+        let err = solve(input, &mut Trace::disabled(), &Budget::unlimited().with_step_limit(4)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
     }
 }
\ No newline at end of file
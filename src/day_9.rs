@@ -1,9 +1,344 @@
-pub fn solve(input: &str) {
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+
+use crate::answers::Answers;
+use crate::budget::Budget;
+use crate::explain::Trace;
+
+/// A history and its rows of differences, down to the first row of zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DifferencePyramid {
+    rows: Vec<Vec<i64>>,
+    reaches_zeros: bool,
+}
+
+impl DifferencePyramid {
+    /// Fails with `FitError::Overflow` when a difference does not fit in an `i64`.
+    pub fn new(history: &[i64]) -> Result<Self, FitError> {
+        let mut rows = vec![history.to_vec()];
+        let mut reaches_zeros = history.iter().all(|&value| value == 0);
+        while !reaches_zeros {
+            let last_row = rows.last().unwrap();
+            if last_row.len() < 2 {
+                break;
+            }
+            let next_row: Vec<i64> = last_row.windows(2)
+                .map(|pair| pair[1].checked_sub(pair[0]))
+                .collect::<Option<_>>()
+                .ok_or(FitError::Overflow)?;
+            reaches_zeros = next_row.iter().all(|&value| value == 0);
+            rows.push(next_row);
+        }
+        Ok(DifferencePyramid { rows, reaches_zeros })
+    }
+
+    /// The degree of the polynomial behind the history, or `None` when the differences
+    /// run out of values before reaching a row of zeros and the input is suspect.
+    pub fn degree(&self) -> Option<usize> {
+        if self.reaches_zeros {
+            Some((self.rows.len() - 1).saturating_sub(1))
+        } else {
+            None
+        }
+    }
+
+    /// The value after the history, `None` if it does not fit in an `i64`.
+    pub fn next_value(&self) -> Option<i64> {
+        self.rows.iter().filter_map(|row| row.last()).try_fold(0i64, |sum, &last| sum.checked_add(last))
+    }
+
+    /// The value before the history, `None` if it does not fit in an `i64`.
+    pub fn previous_value(&self) -> Option<i64> {
+        self.rows.iter().rev().filter_map(|row| row.first()).try_fold(0i64, |below, &first| first.checked_sub(below))
+    }
+
+    /// Draws the pyramid like the puzzle does, the extrapolated values in brackets on either side.
+    pub fn render(&self) -> String {
+        // Wide enough that extrapolating a pyramid whose rows all fit in `i64` cannot overflow.
+        let mut next: i128 = 0;
+        let mut previous: i128 = 0;
+        let mut extrapolated = vec![(0, 0); self.rows.len()];
+        for (row_idx, row) in self.rows.iter().enumerate().rev() {
+            next += row.last().copied().unwrap_or(0) as i128;
+            previous = row.first().copied().unwrap_or(0) as i128 - previous;
+            extrapolated[row_idx] = (previous, next);
+        }
+
+        let cells: Vec<Vec<String>> = self.rows.iter().zip(&extrapolated)
+            .map(|(row, &(previous, next))| {
+                std::iter::once(format!("[{}]", previous))
+                    .chain(row.iter().map(|value| value.to_string()))
+                    .chain(std::iter::once(format!("[{}]", next)))
+                    .collect()
+            })
+            .collect();
+        let width = cells.iter().flatten().map(String::len).max().unwrap_or(0) + 2;
+
+        let mut text = String::new();
+        for (row_idx, row) in cells.iter().enumerate() {
+            text.push_str(&" ".repeat(row_idx * width / 2));
+            for cell in row {
+                text.push_str(&format!("{:>width$}", cell, width = width));
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FitError {
+    Empty,
+    DegreeTooHigh { len: usize },
+    Overflow,
+}
+
+impl fmt::Display for FitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FitError::Empty => write!(f, "empty history"),
+            FitError::DegreeTooHigh { len } => write!(f, "{} values are not enough to pin down the polynomial's degree", len),
+            FitError::Overflow => write!(f, "the differences do not fit in 64 bits"),
+        }
+    }
+}
+
+impl std::error::Error for FitError {}
+
+/// The minimal degree polynomial through a history, the value at index `x` being `history[x]`.
+/// Coefficients are exact rationals, lowest power first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polynomial {
+    coefficients: Vec<BigRational>,
+}
+
+impl Polynomial {
+    /// Lagrange interpolation through the first `degree + 1` values, the degree being the one
+    /// the difference pyramid settles on. Histories that never settle are rejected.
+    pub fn fit(history: &[i64]) -> Result<Self, FitError> {
+        if history.is_empty() {
+            return Err(FitError::Empty);
+        }
+        let degree = DifferencePyramid::new(history)?.degree()
+            .ok_or(FitError::DegreeTooHigh { len: history.len() })?;
+
+        let mut coefficients = vec![BigRational::zero(); degree + 1];
+        for (i, &value) in history.iter().enumerate().take(degree + 1) {
+            // The basis polynomial that is 1 at i and 0 at every other node.
+            let mut basis = vec![BigRational::one()];
+            let mut denominator = BigInt::one();
+            for j in (0..=degree).filter(|&j| j != i) {
+                let mut next = vec![BigRational::zero(); basis.len() + 1];
+                for (power, coefficient) in basis.iter().enumerate() {
+                    next[power + 1] += coefficient;
+                    next[power] -= coefficient * BigInt::from(j);
+                }
+                basis = next;
+                denominator *= BigInt::from(i as i64 - j as i64);
+            }
+            let scale = BigRational::new(BigInt::from(value), denominator);
+            for (total, coefficient) in coefficients.iter_mut().zip(basis) {
+                *total += coefficient * &scale;
+            }
+        }
+        while coefficients.len() > 1 && coefficients.last().unwrap().is_zero() {
+            coefficients.pop();
+        }
+        Ok(Polynomial { coefficients })
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coefficients.len() - 1
+    }
+
+    /// The value at any index, `-1` being the value before the history and `len` the one after it.
+    pub fn evaluate(&self, x: i64) -> BigRational {
+        let x = BigRational::from_integer(BigInt::from(x));
+        self.coefficients.iter().rev().fold(BigRational::zero(), |acc, coefficient| acc * &x + coefficient)
+    }
+}
+
+/// The closed form, e.g. `(1/2)x^2 + (3/2)x + 1`.
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut terms = self.coefficients.iter().enumerate().rev().filter(|(_, coefficient)| !coefficient.is_zero()).peekable();
+        if terms.peek().is_none() {
+            return write!(f, "0");
+        }
+        let mut is_first = true;
+        for (power, coefficient) in terms {
+            let sign = match (is_first, coefficient.is_negative()) {
+                (true, true) => "-",
+                (true, false) => "",
+                (false, true) => " - ",
+                (false, false) => " + ",
+            };
+            is_first = false;
+            let magnitude = coefficient.abs();
+            let magnitude = match (power, magnitude.is_integer()) {
+                (0, _) => magnitude.to_string(),
+                _ if magnitude.is_one() => String::new(),
+                (_, true) => magnitude.to_string(),
+                (_, false) => format!("({})", magnitude),
+            };
+            let variable = match power {
+                0 => String::new(),
+                1 => String::from("x"),
+                power => format!("x^{}", power),
+            };
+            write!(f, "{}{}{}", sign, magnitude, variable)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn solve<R: BufRead>(input: R, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
     println!("Solving Day 9 Problems...");
+    let mut part_one_answer: i64 = 0;
+    let mut part_two_answer: i64 = 0;
+
+    for (line_idx, history) in read_histories(input) {
+        budget.step()?;
+        let overflow = || io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_idx + 1, FitError::Overflow));
+        let pyramid = DifferencePyramid::new(&history?).map_err(|_| overflow())?;
+        let (previous_value, next_value) = pyramid.previous_value().zip(pyramid.next_value()).ok_or_else(overflow)?;
+        trace.note(|| format!("history {}: degree {}, previous {}, next {}", line_idx + 1,
+                              pyramid.degree().map_or(String::from("unknown"), |degree| degree.to_string()),
+                              previous_value, next_value));
+        part_one_answer = part_one_answer.checked_add(next_value).ok_or_else(overflow)?;
+        part_two_answer = part_two_answer.checked_add(previous_value).ok_or_else(overflow)?;
+    }
+
+    println!("The answer to the first gold star for day 9 is: {}", part_one_answer);
+    println!("The answer to the second gold star for day 9 is: {}", part_two_answer);
+    Ok(Answers::new(part_one_answer, part_two_answer))
+}
+
+/// Solves both parts by evaluating each history's exact polynomial, checking every
+/// extrapolated value against the difference pyramid.
+pub fn solve_exact<R: BufRead>(input: R, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
+    println!("Solving Day 9 Problems exactly...");
+    let mut part_one_answer = BigInt::zero();
+    let mut part_two_answer = BigInt::zero();
+
+    for (line_idx, history) in read_histories(input) {
+        budget.step()?;
+        let history = history?;
+        let invalid_data = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_idx + 1, message));
+        let polynomial = Polynomial::fit(&history).map_err(|err| invalid_data(err.to_string()))?;
+        let pyramid = DifferencePyramid::new(&history).map_err(|err| invalid_data(err.to_string()))?;
+        let next_value = polynomial.evaluate(history.len() as i64);
+        let previous_value = polynomial.evaluate(-1);
+        trace.note(|| format!("history {}: {}, previous {}, next {}", line_idx + 1, polynomial, previous_value, next_value));
+
+        // Extrapolating past `i64` is fine here, the pyramid's answers are only compared.
+        let expected = (pyramid.previous_value().map(BigInt::from), pyramid.next_value().map(BigInt::from));
+        if !next_value.is_integer() || !previous_value.is_integer()
+            || expected.0.as_ref().is_some_and(|expected| previous_value.to_integer() != *expected)
+            || expected.1.as_ref().is_some_and(|expected| next_value.to_integer() != *expected) {
+            return Err(invalid_data(format!("polynomial gives {} and {} but the pyramid gives {:?} and {:?}",
+                                            previous_value, next_value, expected.0, expected.1)));
+        }
+        part_one_answer += next_value.to_integer();
+        part_two_answer += previous_value.to_integer();
+    }
+
+    println!("The answer to the first gold star for day 9 is: {}", part_one_answer);
+    println!("The answer to the second gold star for day 9 is: {}", part_two_answer);
+    Ok(Answers::new(part_one_answer, part_two_answer))
+}
+
+/// Prints every history's difference pyramid along with the degree it was fitted with.
+pub fn display<R: BufRead, W: Write>(input: R, out: &mut W) -> io::Result<()> {
+    for (line_idx, history) in read_histories(input) {
+        let history = history?;
+        let pyramid = DifferencePyramid::new(&history).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_idx + 1, err)))?;
+        match Polynomial::fit(&history) {
+            Ok(polynomial) => writeln!(out, "History {}: degree {}, p(x) = {}", line_idx + 1, polynomial.degree(), polynomial)?,
+            Err(_) => writeln!(out, "History {}: suspect, never reaches all zeros within {} values", line_idx + 1, history.len())?,
+        }
+        writeln!(out, "{}", pyramid.render())?;
+    }
+    Ok(())
+}
 
-    let lines = input.lines();
+fn read_histories<R: BufRead>(input: R) -> impl Iterator<Item=(usize, io::Result<Vec<i64>>)> {
+    input.lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(line_idx, line)| (line_idx, line.and_then(|line| parse_history(&line, line_idx))))
+}
+
+fn parse_history(line: &str, line_idx: usize) -> io::Result<Vec<i64>> {
+    line.split_whitespace()
+        .map(|value| value.parse().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("line {}: invalid value \"{}\"", line_idx + 1, value))
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let histories = [[0, 3, 6, 9, 12, 15], [1, 3, 6, 10, 15, 21], [10, 13, 16, 21, 30, 45]];
+        let pyramids: Vec<_> = histories.iter().map(|history| DifferencePyramid::new(history).unwrap()).collect();
+        assert_eq!(pyramids.iter().map(|pyramid| pyramid.next_value().unwrap()).sum::<i64>(), 114);
+        assert_eq!(pyramids.iter().map(|pyramid| pyramid.previous_value().unwrap()).sum::<i64>(), 2);
+        assert_eq!(pyramids.iter().map(|pyramid| pyramid.degree().unwrap()).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_suspect_history() {
+        let pyramid = DifferencePyramid::new(&[1, 2, 4, 8]).unwrap();
+        assert_eq!(pyramid.degree(), None);
+        assert_eq!(DifferencePyramid::new(&[5]).unwrap().degree(), None);
+        assert_eq!(DifferencePyramid::new(&[5, 5]).unwrap().degree(), Some(0));
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(DifferencePyramid::new(&[i64::MIN, i64::MAX]), Err(FitError::Overflow));
+        assert_eq!(Polynomial::fit(&[i64::MIN, i64::MAX]), Err(FitError::Overflow));
+        let pyramid = DifferencePyramid::new(&[i64::MAX - 1, i64::MAX]).unwrap();
+        assert_eq!(pyramid.next_value(), None);
+        assert_eq!(pyramid.previous_value(), Some(i64::MAX - 2));
+        assert!(solve("0 4611686018427387904 -4611686018427387904".as_bytes(), &mut Trace::disabled(), &Budget::unlimited()).is_err());
+    }
+
+    #[test]
+    fn test_polynomial_fit() {
+        let polynomial = Polynomial::fit(&[1, 3, 6, 10, 15, 21]).unwrap();
+        assert_eq!(polynomial.to_string(), "(1/2)x^2 + (3/2)x + 1");
+        assert_eq!(polynomial.evaluate(6), BigRational::from_integer(BigInt::from(28)));
+        assert_eq!(polynomial.evaluate(-1), BigRational::from_integer(BigInt::from(0)));
+        assert_eq!(polynomial.evaluate(-3), BigRational::from_integer(BigInt::from(1)));
+
+        assert_eq!(Polynomial::fit(&[10, 13, 16, 21, 30, 45]).unwrap().to_string(), "(1/3)x^3 - x^2 + (11/3)x + 10");
+        assert_eq!(Polynomial::fit(&[-4, -4]).unwrap().to_string(), "-4");
+        assert_eq!(Polynomial::fit(&[0, 0, 0]).unwrap().to_string(), "0");
+    }
+
+    #[test]
+    fn test_polynomial_rejects_unsettled_history() {
+        assert_eq!(Polynomial::fit(&[1, 2, 4, 8]), Err(FitError::DegreeTooHigh { len: 4 }));
+        assert_eq!(Polynomial::fit(&[]), Err(FitError::Empty));
+    }
 
-    for line in lines {
-        println!("{}",line);
+    #[test]
+    fn test_render() {
+        let pyramid = DifferencePyramid::new(&[0, 3, 6, 9]).unwrap();
+        let expected = concat!(
+            "  [-3]     0     3     6     9  [12]\n",
+            "      [3]     3     3     3   [3]\n",
+            "         [0]     0     0   [0]\n",
+        );
+        assert_eq!(pyramid.render(), expected);
     }
-}
\ No newline at end of file
+}
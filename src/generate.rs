@@ -0,0 +1,341 @@
+//! Random, well formed puzzle inputs for stress and differential testing.
+//!
+//! Every generator is deterministic for a given seed, so a failing input can be
+//! reproduced from the seed alone. `size` is roughly the number of lines, or the
+//! side of the grid for day 3.
+
+use std::collections::HashSet;
+
+use crate::day_1::ENGLISH;
+
+/// SplitMix64, small and stable across releases so seeds keep producing the same inputs.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `low..high`. `high` must be above `low`.
+    pub fn range(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next_u64() % (high - low)
+    }
+
+    /// True with probability `numerator / denominator`.
+    pub fn chance(&mut self, numerator: u64, denominator: u64) -> bool {
+        self.range(0, denominator) < numerator
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.range(0, items.len() as u64) as usize]
+    }
+}
+
+/// The input for `day`, or `None` for days without a generator.
+pub fn generate(day: i32, size: usize, seed: u64) -> Option<String> {
+    let mut rng = Rng::new(seed);
+    let size = size.max(1);
+    let input = match day {
+        1 => calibration_lines(&mut rng, size),
+        2 => cube_games(&mut rng, size),
+        3 => schematic(&mut rng, size),
+        4 => scratchcards(&mut rng, size),
+        5 => almanac(&mut rng, size),
+        6 => races(&mut rng, size),
+        7 => camel_hands(&mut rng, size),
+        8 => desert_network(&mut rng, size),
+        9 => histories(&mut rng, size),
+        _ => return None,
+    };
+    Some(input)
+}
+
+/// Lowercase noise with spelled out and plain digits mixed in. Every line holds at
+/// least one plain digit, which part one needs, and zero never appears since the
+/// calibration values do not count it.
+pub fn calibration_lines(rng: &mut Rng, size: usize) -> String {
+    let mut input = String::new();
+    for _ in 0..size {
+        let mut line = String::new();
+        let pieces = rng.range(1, 8);
+        let digit_at = rng.range(0, pieces);
+        for piece in 0..pieces {
+            if piece == digit_at || rng.chance(1, 4) {
+                line.push(char::from(b'0' + rng.range(1, 10) as u8));
+            } else if rng.chance(1, 2) {
+                let word: &&str = rng.pick(&ENGLISH[1..]);
+                line.push_str(word);
+            } else {
+                for _ in 0..rng.range(1, 6) {
+                    line.push(char::from(b'a' + rng.range(0, 26) as u8));
+                }
+            }
+        }
+        input.push_str(&line);
+        input.push('\n');
+    }
+    input
+}
+
+pub fn cube_games(rng: &mut Rng, size: usize) -> String {
+    let mut input = String::new();
+    for id in 1..=size {
+        let draws: Vec<String> = (0..rng.range(1, 7))
+            .map(|_| {
+                let mut colours = vec!["red", "green", "blue"];
+                let shown = rng.range(1, 4) as usize;
+                (0..shown)
+                    .map(|_| {
+                        let colour = colours.remove(rng.range(0, colours.len() as u64) as usize);
+                        format!("{} {}", rng.range(1, 21), colour)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .collect();
+        input.push_str(&format!("Game {}: {}\n", id, draws.join("; ")));
+    }
+    input
+}
+
+/// A `size` by `size` engine schematic. Numbers are up to three digits and never touch
+/// each other on the same row.
+pub fn schematic(rng: &mut Rng, size: usize) -> String {
+    const SYMBOLS: [char; 10] = ['*', '#', '+', '$', '/', '@', '%', '=', '&', '-'];
+    let mut input = String::new();
+    for _ in 0..size {
+        let mut row = String::new();
+        while row.len() < size {
+            let room = size - row.len();
+            if room >= 2 && rng.chance(1, 4) {
+                let digits = rng.range(1, room.min(4) as u64) as usize;
+                for _ in 0..digits {
+                    row.push(char::from(b'0' + rng.range(0, 10) as u8));
+                }
+                row.push('.');
+            } else if rng.chance(1, 8) {
+                row.push(*rng.pick(&SYMBOLS));
+            } else {
+                row.push('.');
+            }
+        }
+        input.push_str(&row);
+        input.push('\n');
+    }
+    input
+}
+
+/// Cards with 10 winning numbers and 25 in hand, like the puzzle's. Most cards win nothing,
+/// keeping the copies of later cards from growing past `u64` on long piles.
+pub fn scratchcards(rng: &mut Rng, size: usize) -> String {
+    let mut input = String::new();
+    for id in 1..=size {
+        let mut numbers: Vec<u64> = Vec::new();
+        let mut seen = HashSet::new();
+        while numbers.len() < 35 {
+            let number = rng.range(1, 100);
+            if seen.insert(number) {
+                numbers.push(number);
+            }
+        }
+        let matches = if rng.chance(3, 5) { 0 } else { rng.range(1, 3) as usize };
+        let winning = &numbers[..10];
+        let mut in_hand: Vec<u64> = numbers[10..35 - matches].to_vec();
+        in_hand.extend(&winning[..matches]);
+        let format_numbers = |numbers: &[u64]| numbers.iter().map(|number| format!("{:>2}", number)).collect::<Vec<_>>().join(" ");
+        input.push_str(&format!("Card {:>3}: {} | {}\n", id, format_numbers(winning), format_numbers(&in_hand)));
+    }
+    input
+}
+
+/// Seeds and seven maps over values below 2^32. Each map's source ranges are disjoint.
+pub fn almanac(rng: &mut Rng, size: usize) -> String {
+    const SPACE: u64 = 1 << 32;
+    const STAGES: [&str; 7] = ["seed-to-soil", "soil-to-fertilizer", "fertilizer-to-water", "water-to-light",
+        "light-to-temperature", "temperature-to-humidity", "humidity-to-location"];
+    let seed_pairs = (size / 10).clamp(1, 20);
+    let seeds: Vec<String> = (0..seed_pairs)
+        .map(|_| {
+            let start = rng.range(0, SPACE / 2);
+            format!("{} {}", start, rng.range(1, SPACE / 64))
+        })
+        .collect();
+    let mut input = format!("seeds: {}\n", seeds.join(" "));
+
+    let lines_per_map = (size / 7).max(1);
+    for stage in STAGES {
+        input.push_str(&format!("\n{} map:\n", stage));
+        let mut cuts: Vec<u64> = (0..lines_per_map * 2).map(|_| rng.range(0, SPACE / 2)).collect();
+        cuts.sort_unstable();
+        cuts.dedup();
+        for pair in cuts.chunks_exact(2) {
+            let (source_start, length) = (pair[0], pair[1] - pair[0] + 1);
+            let dest_start = rng.range(0, SPACE / 2);
+            input.push_str(&format!("{} {} {}\n", dest_start, source_start, length));
+        }
+    }
+    input
+}
+
+/// At most four races with two digit times, since part two reads the races as one
+/// long race and the puzzle's own solvers count its ways one by one.
+pub fn races(rng: &mut Rng, size: usize) -> String {
+    let count = size.clamp(1, 4);
+    let times: Vec<u64> = (0..count).map(|_| rng.range(10, 100)).collect();
+    let distances: Vec<u64> = times.iter().map(|&time| rng.range(time, time * time / 4)).collect();
+    let format_row = |numbers: &[u64]| numbers.iter().map(|number| format!("{:>5}", number)).collect::<String>();
+    format!("Time:    {}\nDistance:{}\n", format_row(&times), format_row(&distances))
+}
+
+pub fn camel_hands(rng: &mut Rng, size: usize) -> String {
+    const CARDS: [char; 13] = ['2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K', 'A'];
+    let mut input = String::new();
+    for _ in 0..size {
+        let label: String = (0..5).map(|_| *rng.pick(&CARDS)).collect();
+        input.push_str(&format!("{} {}\n", label, rng.range(1, 1001)));
+    }
+    input
+}
+
+/// A network in which every ghost ends up on its own loop, like the puzzle's.
+///
+/// Each ghost walks a chain from its `..A` node to its `..Z` node that is a whole
+/// number of instruction passes long, then loops back to the start of the chain,
+/// so both parts terminate. The child the instructions do not take points anywhere.
+pub fn desert_network(rng: &mut Rng, size: usize) -> String {
+    const PRIMES: [u64; 8] = [2, 3, 5, 7, 11, 13, 17, 19];
+    let instructions: String = (0..(size / 20).clamp(2, 300)).map(|_| if rng.chance(1, 2) { 'L' } else { 'R' }).collect();
+    let ghosts = (size / 100).clamp(1, 6);
+
+    let mut used = HashSet::new();
+    let mut chains: Vec<Vec<String>> = Vec::new();
+    for ghost in 0..ghosts {
+        let passes = *rng.pick(&PRIMES) as usize;
+        let inner = passes * instructions.len() - 1;
+        let mut chain = vec![if ghost == 0 { String::from("AAA") } else { unique_name(rng, &mut used, Some('A')) }];
+        chain.extend((0..inner).map(|_| unique_name(rng, &mut used, None)));
+        chain.push(if ghost == 0 { String::from("ZZZ") } else { unique_name(rng, &mut used, Some('Z')) });
+        chains.push(chain);
+    }
+    let all_names: Vec<String> = chains.iter().flatten().cloned().collect();
+
+    let mut lines = Vec::new();
+    for chain in &chains {
+        for (position, name) in chain.iter().enumerate() {
+            // The `..Z` node sits at a whole number of passes, so it leaves like the `..A` node does.
+            let next = if position + 1 < chain.len() { &chain[position + 1] } else { &chain[1] };
+            let other = rng.pick(&all_names);
+            let line = match instructions.as_bytes()[position % instructions.len()] {
+                b'L' => format!("{} = ({}, {})", name, next, other),
+                _ => format!("{} = ({}, {})", name, other, next),
+            };
+            lines.push(line);
+        }
+    }
+    // Shuffle so the node order gives nothing away.
+    for idx in (1..lines.len()).rev() {
+        let other = rng.range(0, idx as u64 + 1) as usize;
+        lines.swap(idx, other);
+    }
+    format!("{}\n\n{}\n", instructions, lines.join("\n"))
+}
+
+/// A fresh three character node name. Only the ghosts' start and end nodes end in `A` or `Z`.
+fn unique_name(rng: &mut Rng, used: &mut HashSet<String>, last: Option<char>) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    loop {
+        let mut name: String = (0..2).map(|_| char::from(*rng.pick(ALPHABET))).collect();
+        match last {
+            Some(last) => name.push(last),
+            None => loop {
+                let c = char::from(*rng.pick(ALPHABET));
+                if c != 'A' && c != 'Z' {
+                    name.push(c);
+                    break;
+                }
+            },
+        }
+        if name != "AAA" && name != "ZZZ" && used.insert(name.clone()) {
+            return name;
+        }
+    }
+}
+
+/// Histories of 21 values sampled from polynomials of degree at most 6 with small integer coefficients.
+pub fn histories(rng: &mut Rng, size: usize) -> String {
+    let mut input = String::new();
+    for _ in 0..size {
+        let degree = rng.range(0, 7) as usize;
+        let coefficients: Vec<i64> = (0..=degree).map(|_| rng.range(0, 11) as i64 - 5).collect();
+        let values: Vec<String> = (0..21i64)
+            .map(|x| coefficients.iter().rev().fold(0, |value, &coefficient| value * x + coefficient).to_string())
+            .collect();
+        input.push_str(&values.join(" "));
+        input.push('\n');
+    }
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::budget::Budget;
+    use crate::explain::Trace;
+    use crate::registry;
+
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        for day in 1..=9 {
+            assert_eq!(generate(day, 50, 7), generate(day, 50, 7));
+            assert_ne!(generate(day, 50, 7), generate(day, 50, 8), "day {}", day);
+        }
+        assert_eq!(generate(10, 50, 7), None);
+    }
+
+    #[test]
+    fn test_generated_inputs_solve() {
+        for seed in 0..3 {
+            let input = |day| generate(day, 200, seed).unwrap();
+            crate::day_1::solve(input(1).as_bytes(), &mut Trace::disabled(), &Budget::unlimited()).unwrap();
+            crate::day_2::solve(input(2).as_bytes(), &crate::day_2::CubeCounts::elf_bag(), &mut Trace::disabled(), &Budget::unlimited()).unwrap();
+            crate::day_3::solve(input(3).trim(), &mut Trace::disabled(), &Budget::unlimited()).unwrap();
+            crate::day_4::solve(input(4).as_bytes(), &mut Trace::disabled(), &Budget::unlimited()).unwrap();
+            crate::day_5::solve(input(5).trim(), &mut Trace::disabled(), &Budget::unlimited()).unwrap();
+            crate::day_7::solve(input(7).as_bytes(), &mut Trace::disabled(), &Budget::unlimited()).unwrap();
+            crate::day_8::solve(input(8).trim(), &mut Trace::disabled(), &Budget::unlimited()).unwrap();
+            crate::day_9::solve_exact(input(9).as_bytes(), &mut Trace::disabled(), &Budget::unlimited()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_day_6_implementations_agree() {
+        for seed in 0..20 {
+            // Two races keep the concatenated part two race short enough for the naive loop.
+            let input = generate(6, 2, seed).unwrap();
+            let answers: Vec<_> = registry::implementations(6).iter()
+                .map(|implementation| (implementation.answers)(&input, &mut Trace::disabled(), &Budget::unlimited()).unwrap())
+                .collect();
+            assert!(answers.windows(2).all(|pair| pair[0] == pair[1]), "seed {}: {:?}", seed, answers);
+        }
+    }
+
+    #[test]
+    fn test_desert_network_ghosts_loop() {
+        let input = generate(8, 500, 3).unwrap();
+        let starts = input.lines().filter(|line| line.split(' ').next().is_some_and(|name| name.ends_with('A'))).count();
+        assert_eq!(starts, 5);
+        assert!(input.lines().any(|line| line.starts_with("AAA = (")));
+    }
+}
@@ -1,9 +1,4 @@
-pub(crate) fn filter_digits_from_str(string: &str) -> Vec<u32> {
-    string.chars()
-        .filter(|x| x.is_digit(10))
-        .map(|x| x.to_digit(10).unwrap())
-        .collect()
-}
+use std::io;
 
 pub(crate) fn get_num_arr_from_space_seperated_str(string: &str) -> Vec<u32> {
     let mut num_arr = Vec::new();
@@ -16,10 +11,7 @@ pub(crate) fn get_num_arr_from_space_seperated_str(string: &str) -> Vec<u32> {
     num_arr
 }
 
-pub(crate) fn convert_num_arr_to_num(arr: Vec<u32>) -> u32 {
-    let mut num = 0;
-    for curr in arr {
-        num = num * 10 + curr
-    }
-    return num;
-}
\ No newline at end of file
+/// An `InvalidData` error, how every day reports input it cannot solve.
+pub(crate) fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
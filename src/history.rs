@@ -0,0 +1,281 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::answers::Answers;
+use crate::helper::invalid_data;
+
+/// Where runs are recorded unless the runner is told otherwise.
+pub const DEFAULT_PATH: &str = "data/history.jsonl";
+
+/// One run of one day, a line of the history file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    /// Seconds since the Unix epoch when the run finished.
+    pub timestamp: u64,
+    pub day: i32,
+    pub input: String,
+    pub input_hash: String,
+    /// The commit checked out, with `-dirty` when the tree had changes, `None` outside a git checkout.
+    pub commit: Option<String>,
+    /// The options that can change the answers or the time taken, runs are only compared with
+    /// runs that had the same ones.
+    #[serde(default)]
+    pub options: Vec<String>,
+    pub elapsed_secs: f64,
+    /// How long each part took, for days that work the parts out separately.
+    #[serde(default)]
+    pub part_secs: [Option<f64>; 2],
+    pub answers: Answers,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Record {
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64(self.elapsed_secs)
+    }
+
+    /// How long part `part` took, counting from 1.
+    pub fn part_elapsed(&self, part: usize) -> Option<Duration> {
+        self.part_secs.get(part - 1).copied().flatten().map(Duration::from_secs_f64)
+    }
+
+    /// Runs of the same day on the same input with the same options should give the same answers.
+    fn same_run_as(&self, other: &Record) -> bool {
+        self.day == other.day && self.input_hash == other.input_hash && self.options == other.options
+    }
+}
+
+/// Passes reads through to `inner` while hashing every byte read with 64 bit FNV-1a, so a
+/// day's input is hashed as it is solved. Unlike the standard library's hasher FNV-1a is
+/// fixed, so hashes written by older builds still compare.
+pub struct HashingReader<R> {
+    inner: R,
+    hash: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> Self {
+        HashingReader { inner, hash: 0xcbf2_9ce4_8422_2325 }
+    }
+
+    /// Reads whatever was left unread, so the hash covers the whole input, and returns it in hex.
+    pub fn finish(mut self) -> io::Result<String> {
+        io::copy(&mut self, &mut io::sink())?;
+        Ok(format!("{:016x}", self.hash))
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hash = buf[..read].iter().fold(self.hash, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3));
+        Ok(read)
+    }
+}
+
+/// The hash `HashingReader` gives everything in `input`.
+pub fn input_hash<R: Read>(input: R) -> io::Result<String> {
+    HashingReader::new(input).finish()
+}
+
+/// The commit checked out in the current directory, `None` if git cannot tell.
+pub fn git_commit() -> Option<String> {
+    let output = Command::new("git").args(["rev-parse", "--short=12", "HEAD"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let commit = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    let dirty = Command::new("git").args(["status", "--porcelain", "--untracked-files=no"]).output()
+        .is_ok_and(|output| !output.stdout.is_empty());
+    Some(if dirty { format!("{}-dirty", commit) } else { commit })
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+}
+
+/// Adds `record` to the end of the history at `path`, creating it if needed.
+pub fn append(path: &Path, record: &Record) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record).map_err(io::Error::other)?)
+}
+
+/// Every record in the history at `path`, oldest first. A missing file has no history.
+pub fn load(path: &Path) -> io::Result<Vec<Record>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    contents.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_idx, line)| serde_json::from_str(line)
+            .map_err(|err| invalid_data(format!("{} line {}: {}", path.display(), line_idx + 1, err))))
+        .collect()
+}
+
+/// `timestamp` as a UTC date and time, `2023-12-01 05:00:00`.
+pub fn format_timestamp(timestamp: u64) -> String {
+    let (days, seconds) = (timestamp / 86_400, timestamp % 86_400);
+    // Days since the epoch to a civil date, counting eras of 400 years from March 2000.
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+fn percent_change(old: Duration, new: Duration) -> String {
+    if old.is_zero() {
+        return String::new();
+    }
+    format!("{:+.1}%", (new.as_secs_f64() / old.as_secs_f64() - 1.0) * 100.0)
+}
+
+/// Prints every recorded run of each of `days` oldest first, how much slower or faster it was
+/// than the run before it on the same input, and any answer that changed since then, followed
+/// by how the time taken on each input changed from its first run to its latest.
+pub fn report<W: Write>(records: &[Record], days: &[i32], out: &mut W) -> io::Result<()> {
+    for &day in days {
+        let runs: Vec<&Record> = records.iter().filter(|record| record.day == day).collect();
+        writeln!(out, "Day {}", day)?;
+        if runs.is_empty() {
+            writeln!(out, "No runs recorded")?;
+            writeln!(out)?;
+            continue;
+        }
+        writeln!(out, "{:<19}  {:<18}  {:<36}  {:>12}  {:>8}", "when", "commit", "input", "time", "change")?;
+        for (idx, run) in runs.iter().enumerate() {
+            let previous = runs[..idx].iter().rev().find(|previous| previous.same_run_as(run));
+            let change = previous.map_or_else(String::new, |previous| percent_change(previous.elapsed(), run.elapsed()));
+            let mut input = run.input.clone();
+            if !run.options.is_empty() {
+                input = format!("{} [{}]", input, run.options.join(" "));
+            }
+            writeln!(out, "{:<19}  {:<18}  {:<36}  {:>12}  {:>8}", format_timestamp(run.timestamp),
+                     run.commit.as_deref().unwrap_or("-"), input, format!("{:.3?}", run.elapsed()), change)?;
+            if let Some(error) = &run.error {
+                writeln!(out, "    failed: {}", error)?;
+            }
+            let Some(previous) = previous else {
+                continue;
+            };
+            for (part, name) in [(1, "part one"), (2, "part two")] {
+                if let Some(old) = previous.answers.part(part) {
+                    if run.answers.part(part) != Some(old) {
+                        writeln!(out, "    ! {} changed from {} to {} on the same input", name, old,
+                                 run.answers.part(part).unwrap_or("no answer"))?;
+                    }
+                }
+            }
+        }
+
+        let mut by_input: BTreeMap<(&str, &str, &[String]), Vec<&Record>> = BTreeMap::new();
+        for run in &runs {
+            by_input.entry((run.input.as_str(), run.input_hash.as_str(), run.options.as_slice())).or_default().push(run);
+        }
+        for ((input, hash, options), runs) in by_input {
+            let mut input = input.to_string();
+            if !options.is_empty() {
+                input = format!("{} [{}]", input, options.join(" "));
+            }
+            let (first, latest) = (runs[0].elapsed(), runs[runs.len() - 1].elapsed());
+            let fastest = runs.iter().map(|run| run.elapsed()).min().unwrap();
+            writeln!(out, "{} ({}): {} runs, first {:.3?}, latest {:.3?} ({}), fastest {:.3?}", input, hash.get(..8).unwrap_or(hash),
+                     runs.len(), first, latest, percent_change(first, latest), fastest)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::io::BufRead;
+
+    use super::*;
+
+    fn record(timestamp: u64, input_hash: &str, elapsed_secs: f64, answers: Answers) -> Record {
+        Record {
+            timestamp,
+            day: 6,
+            input: String::from("data/day_6.txt"),
+            input_hash: String::from(input_hash),
+            commit: Some(String::from("4ff7996")),
+            options: Vec::new(),
+            elapsed_secs,
+            part_secs: [None, None],
+            answers,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_input_hash() {
+        assert_eq!(input_hash(&b""[..]).unwrap(), "cbf29ce484222325");
+        assert_eq!(input_hash(&b"a"[..]).unwrap(), "af63dc4c8601ec8c");
+        assert_ne!(input_hash(&b"Time: 7\n"[..]).unwrap(), input_hash(&b"Time: 8\n"[..]).unwrap());
+
+        // Bytes the solver left unread still count.
+        let mut reader = HashingReader::new(&b"Time: 7\nDistance: 9\n"[..]);
+        io::BufReader::with_capacity(4, &mut reader).read_line(&mut String::new()).unwrap();
+        assert_eq!(reader.finish().unwrap(), input_hash(&b"Time: 7\nDistance: 9\n"[..]).unwrap());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(1_701_406_800), "2023-12-01 05:00:00");
+        assert_eq!(format_timestamp(951_827_696), "2000-02-29 12:34:56");
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let path = env::temp_dir().join(format!("aoc_history_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(load(&path).unwrap().is_empty());
+        let first = record(1, "aa", 0.5, Answers::new(288, 71503));
+        let second = Record { error: Some(String::from("timed out")), ..record(2, "aa", 1.0, Answers::default()) };
+        append(&path, &first).unwrap();
+        append(&path, &second).unwrap();
+        assert_eq!(load(&path).unwrap(), vec![first, second]);
+
+        fs::write(&path, "{}\n").unwrap();
+        assert_eq!(load(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_report() {
+        let records = vec![
+            record(1_701_406_800, "aa", 0.010, Answers::new(288, 71503)),
+            record(1_701_406_900, "bb", 0.020, Answers::new(1, 2)),
+            record(1_701_407_000, "aa", 0.005, Answers::new(288, 71504)),
+        ];
+        let mut out = Vec::new();
+        report(&records, &[6, 7], &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("-50.0%"), "{}", out);
+        assert!(out.contains("! part two changed from 71503 to 71504 on the same input"), "{}", out);
+        assert!(!out.contains("part one changed"), "{}", out);
+        assert!(out.contains("data/day_6.txt (aa): 2 runs, first 10.000ms, latest 5.000ms (-50.0%), fastest 5.000ms"), "{}", out);
+        assert!(out.contains("Day 7\nNo runs recorded"), "{}", out);
+    }
+}
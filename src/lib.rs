@@ -1,3 +1,4 @@
+pub mod answers;
 pub mod day_1;
 pub mod day_2;
 pub mod day_3;
@@ -7,4 +8,12 @@ pub mod day_5;
 pub mod day_6;
 pub mod day_7;
 pub mod day_8;
-pub mod day_9;
\ No newline at end of file
+pub mod day_9;
+pub mod budget;
+pub mod explain;
+pub mod generate;
+pub mod history;
+pub mod perf;
+pub mod registry;
+pub mod tui;
+pub mod watch;
\ No newline at end of file
@@ -1,44 +1,397 @@
-use std::fs;
-use clap::Parser;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command as Process};
+use std::time::{Duration, Instant};
+use clap::{Parser, Subcommand, ValueEnum};
+
+use advent_of_code_2023::answers::Answers;
+use advent_of_code_2023::budget::Budget;
+use advent_of_code_2023::day_2::CubeCounts;
+use advent_of_code_2023::explain::Trace;
+use advent_of_code_2023::history::{self, Record};
+use advent_of_code_2023::perf::{self, Baseline};
+use advent_of_code_2023::registry;
 
 #[derive(Parser, Debug)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(short = 'd', long, value_delimiter = ',', num_args = 1.., help = "Takes in a comma seperated list of integers representing the days to test")]
     days: Vec<i32>,
+    #[arg(long, default_value = "12 red, 13 green, 14 blue", help = "The cubes in the bag for day 2, written like a draw")]
+    bag: CubeCounts,
+    #[arg(long, value_enum, help = "Prints the intermediate facts behind each answer")]
+    explain: Option<ExplainFormat>,
+    #[arg(long, value_name = "DIR", help = "Writes a visualisation of each day that has one into DIR")]
+    export: Option<PathBuf>,
+    #[arg(long, help = "Prints a visual display of each day that has one after its answers")]
+    display: bool,
+    #[arg(long, help = "Solves day 9 by exact polynomial interpolation, checked against the difference pyramid")]
+    exact: bool,
+    #[arg(long, value_name = "HAND", num_args = 0.., help = "Prints day 7 hand type probabilities over every possible hand, and the percentile of each HAND given")]
+    hand_stats: Option<Vec<String>>,
+    #[arg(long = "impl", value_enum, help = "Picks the implementation of days that have several, or runs them all, checking they agree")]
+    implementation: Option<ImplChoice>,
+    #[arg(long, value_name = "FILE", help = "Reads the input of the single day given from FILE instead of data/day_N.txt")]
+    input: Option<PathBuf>,
+    #[arg(long, value_name = "SECONDS", value_parser = parse_timeout, help = "Gives up on a day after SECONDS, reporting it as timed out")]
+    timeout: Option<Duration>,
+    #[arg(long, global = true, value_name = "FILE", default_value = history::DEFAULT_PATH, help = "Where every run is recorded, and read back by the history command")]
+    history: PathBuf,
+    #[arg(long, help = "Does not record this run in the history")]
+    no_history: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "Prints a random, well formed puzzle input for a day")]
+    Generate {
+        #[arg(short = 'd', long, help = "The day to generate an input for, 1 to 9")]
+        day: i32,
+        #[arg(long, default_value_t = 100, help = "Roughly the number of lines, or the grid side for day 3")]
+        size: usize,
+        #[arg(long, default_value_t = 0, help = "The same seed always generates the same input")]
+        seed: u64,
+    },
+    #[command(about = "Reruns a day on its examples and its input every time its source or input changes")]
+    Watch {
+        #[arg(short = 'd', long, help = "The day to watch")]
+        day: i32,
+    },
+    #[command(about = "Shows how each day's runtime changed over the recorded runs, flagging answers that changed on the same input")]
+    History {
+        #[arg(short = 'd', long, value_delimiter = ',', num_args = 1.., help = "The days to report on, every day run so far if none are given")]
+        days: Vec<i32>,
+    },
+    #[command(about = "Times each day several times and compares the medians with a stored baseline, failing on slowdowns")]
+    Perf {
+        #[arg(long, value_name = "FILE", default_value = "perf.json", help = "The baseline to compare with")]
+        baseline: PathBuf,
+        #[arg(short = 'd', long, value_delimiter = ',', num_args = 1.., help = "The days to time, every solved day if none are given")]
+        days: Vec<i32>,
+        #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..), help = "How many times to run each day")]
+        runs: u32,
+        #[arg(long, value_name = "PERCENT", default_value_t = 10.0, help = "How much slower than the baseline a median may be before it counts as a regression")]
+        tolerance: f64,
+        #[arg(long, help = "Writes the medians measured into the baseline instead of failing on slowdowns")]
+        update_baseline: bool,
+    },
+    #[command(about = "Browses every day, its answers, timings and traces in the terminal")]
+    Tui,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ExplainFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ImplChoice {
+    Naive,
+    Fast,
+    All,
 }
 
+fn parse_timeout(seconds: &str) -> Result<Duration, String> {
+    let seconds: f64 = seconds.parse().map_err(|_| format!("\"{}\" is not a number of seconds", seconds))?;
+    Duration::try_from_secs_f64(seconds).map_err(|err| err.to_string())
+}
 
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
 
-    let days;
+    match args.command.take() {
+        Some(Command::Generate { day, size, seed }) => {
+            match advent_of_code_2023::generate::generate(day, size, seed) {
+                Some(input) => print!("{}", input),
+                None => println!("No generator for day {}", day),
+            }
+            return;
+        }
+        Some(Command::Watch { day }) => {
+            if let Err(err) = advent_of_code_2023::watch::watch(day) {
+                println!("Stopped watching day {}: {}", day, err);
+            }
+            return;
+        }
+        Some(Command::History { days }) => {
+            if let Err(err) = report_history(&args.history, days) {
+                println!("Failed to report the history: {}", err);
+            }
+            return;
+        }
+        Some(Command::Perf { baseline, days, runs, tolerance, update_baseline }) => {
+            match run_perf(&baseline, days, runs, tolerance / 100.0, update_baseline) {
+                Ok(true) => (),
+                Ok(false) => process::exit(1),
+                Err(err) => {
+                    println!("Failed to check performance: {}", err);
+                    process::exit(2);
+                }
+            }
+            return;
+        }
+        Some(Command::Tui) => {
+            if let Err(err) = advent_of_code_2023::tui::run() {
+                println!("Terminal UI failed: {}", err);
+            }
+            return;
+        }
+        None => (),
+    }
 
-    if !args.days.is_empty() {
-        days = args.days;
+    if let Some(hands) = &args.hand_stats {
+        if let Err(err) = advent_of_code_2023::day_7::stats::report(hands, &mut io::stdout().lock()) {
+            println!("Failed to report hand statistics: {}", err);
+        }
+        return;
+    }
+
+    let days = if !args.days.is_empty() {
+        args.days.clone()
     } else {
-        days = (1..=25).collect();
+        (1..=25).collect()
+    };
+    if args.input.is_some() && days.len() != 1 {
+        println!("--input needs exactly one day");
+        return;
     }
 
     for day in &days {
         println!("Day {}", day);
-        let input_file_path = format!("./data/day_{}.txt", day);
-        let data = fs::read_to_string(&input_file_path);
-        if let Ok(data) = data {
-            let data = data.trim();
-            let func_to_run = match day {
-                1 => advent_of_code_2023::day_1::solve,
-                2 => advent_of_code_2023::day_2::solve,
-                3 => advent_of_code_2023::day_3::solve,
-                4 => advent_of_code_2023::day_4::solve,
-                5 => advent_of_code_2023::day_5::solve,
-                6 => advent_of_code_2023::day_6::solve,
-                7 => advent_of_code_2023::day_7::solve,
-                8 => advent_of_code_2023::day_8::solve,
-                9 => advent_of_code_2023::day_9::solve,
-                _ => unreachable!(),
+        let input_file_path = match &args.input {
+            Some(path) => path.clone(),
+            None => PathBuf::from(format!("./data/day_{}.txt", day)),
+        };
+        if let Ok(file) = File::open(&input_file_path) {
+            if !registry::SOLVED_DAYS.contains(day) {
+                println!("Day {} is not solved yet", day);
+                println!();
+                continue;
+            }
+            let mut trace = if args.explain.is_some() { Trace::enabled() } else { Trace::disabled() };
+            let budget = match args.timeout {
+                Some(timeout) => Budget::unlimited().with_timeout(timeout),
+                None => Budget::unlimited(),
             };
-            func_to_run(data);
+            let start = Instant::now();
+            let mut input = history::HashingReader::new(file);
+            let result = run_day(*day, &mut input, &args, &mut trace, &budget);
+            let elapsed = start.elapsed();
+            let (answers, error) = match result {
+                Ok(answers) => (answers, None),
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    println!("Day {} timed out", day);
+                    (Answers::default(), Some(String::from("timed out")))
+                }
+                Err(err) => {
+                    println!("Failed to solve day {}: {}", day, err);
+                    (Answers::default(), Some(err.to_string()))
+                }
+            };
+            if !args.no_history {
+                let recorded = input.finish().and_then(|input_hash| history::append(&args.history, &Record {
+                    timestamp: history::now(),
+                    day: *day,
+                    input: input_file_path.to_string_lossy().trim_start_matches("./").to_string(),
+                    input_hash,
+                    commit: history::git_commit(),
+                    options: history_options(*day, &args),
+                    elapsed_secs: elapsed.as_secs_f64(),
+                    part_secs: trace.part_times().map(|time| time.map(|time| time.as_secs_f64())),
+                    answers: answers.clone(),
+                    error,
+                }));
+                if let Err(err) = recorded {
+                    println!("Failed to record day {} in the history: {}", day, err);
+                }
+            }
+            match args.explain {
+                Some(ExplainFormat::Text) => print!("{}", trace.render_text()),
+                Some(ExplainFormat::Json) => println!("{{\"day\":{},\"answers\":{},\"trace\":{}}}",
+                                                      day, serde_json::to_string(&answers).unwrap(), trace.render_json()),
+                None => (),
+            }
+            if args.display {
+                if let Err(err) = display_day(*day, &input_file_path, &args) {
+                    println!("Failed to display day {}: {}", day, err);
+                }
+            }
             println!();
         }
     }
 }
+
+fn run_day<R: Read>(day: i32, input: R, args: &Args, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
+    // Line-oriented days stream their input, the rest need the whole grid or almanac at once.
+    let reader = BufReader::new(input);
+    match day {
+        1 => advent_of_code_2023::day_1::solve(reader, trace, budget),
+        2 => advent_of_code_2023::day_2::solve(reader, &args.bag, trace, budget),
+        4 => advent_of_code_2023::day_4::solve(reader, trace, budget),
+        7 => advent_of_code_2023::day_7::solve(reader, trace, budget),
+        9 if args.exact => advent_of_code_2023::day_9::solve_exact(reader, trace, budget),
+        9 => advent_of_code_2023::day_9::solve(reader, trace, budget),
+        _ => {
+            let data = io::read_to_string(reader)?;
+            let data = data.trim();
+            let answers = match args.implementation {
+                Some(choice) if !registry::implementations(day).is_empty() => run_implementations(day, data, choice, trace, budget)?,
+                _ => {
+                    let func_to_run = match day {
+                        3 => advent_of_code_2023::day_3::solve,
+                        5 => advent_of_code_2023::day_5::solve,
+                        6 => advent_of_code_2023::day_6::solve,
+                        8 => advent_of_code_2023::day_8::solve,
+                        _ => unreachable!(),
+                    };
+                    func_to_run(data, trace, budget)?
+                }
+            };
+            if let Some(dir) = &args.export {
+                export_day(day, data, dir)?;
+            }
+            Ok(answers)
+        }
+    }
+}
+
+/// Runs the chosen registered implementations of `day` side by side, timing each one.
+/// With `ImplChoice::All` it is an error for any two of them to disagree.
+fn run_implementations(day: i32, data: &str, choice: ImplChoice, trace: &mut Trace, budget: &Budget) -> io::Result<Answers> {
+    let selected: Vec<&registry::Implementation> = match choice {
+        ImplChoice::All => registry::implementations(day).iter().collect(),
+        choice => {
+            let name = choice.to_possible_value().unwrap();
+            registry::find(day, name.get_name()).into_iter().collect()
+        }
+    };
+    let mut results = Vec::new();
+    for implementation in selected {
+        let start = Instant::now();
+        let answers = trace.section(|| String::from(implementation.name), |trace| (implementation.answers)(data, trace, budget))?;
+        let elapsed = start.elapsed();
+        println!("{:<6} part one: {:<16} part two: {:<16} in {:.3?}", implementation.name, answers.0, answers.1, elapsed);
+        results.push((implementation.name, answers));
+    }
+    if let Some((name, answers)) = results.iter().find(|(_, answers)| *answers != results[0].1) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                  format!("{} answered {:?} but {} answered {:?}", results[0].0, results[0].1, name, answers)));
+    }
+    if choice == ImplChoice::All {
+        println!("All {} implementations agree", results.len());
+    }
+    Ok(results.first().map_or_else(Answers::default, |(_, answers)| Answers::new(answers.0, answers.1)))
+}
+
+/// The options `day` was run with that can change its answers or how long it takes, kept
+/// with its run in the history.
+fn history_options(day: i32, args: &Args) -> Vec<String> {
+    let mut options = Vec::new();
+    if day == 2 {
+        options.push(format!("--bag={}", args.bag));
+    }
+    if day == 9 && args.exact {
+        options.push(String::from("--exact"));
+    }
+    if let Some(choice) = args.implementation.filter(|_| !registry::implementations(day).is_empty()) {
+        options.push(format!("--impl={}", choice.to_possible_value().unwrap().get_name()));
+    }
+    if args.explain.is_some() {
+        options.push(String::from("--explain"));
+    }
+    if args.export.is_some() {
+        options.push(String::from("--export"));
+    }
+    options
+}
+
+fn report_history(path: &Path, days: Vec<i32>) -> io::Result<()> {
+    let records = history::load(path)?;
+    let days = if days.is_empty() {
+        let mut days: Vec<i32> = records.iter().map(|record| record.day).collect();
+        days.sort_unstable();
+        days.dedup();
+        days
+    } else {
+        days
+    };
+    history::report(&records, &days, &mut io::stdout().lock())
+}
+
+/// Runs `days` `runs` times, each run in a child process recording into a scratch history,
+/// so the times compared are the ones the runner measures around each day and not the
+/// start up of the process. Returns whether no day got slower or failed.
+fn run_perf(baseline_path: &Path, days: Vec<i32>, runs: u32, tolerance: f64, update_baseline: bool) -> io::Result<bool> {
+    let baseline = match Baseline::load(baseline_path)? {
+        Some(baseline) => baseline,
+        None if update_baseline => Baseline::default(),
+        None => return Err(io::Error::new(io::ErrorKind::NotFound,
+                                          format!("no baseline at {}, create one with --update-baseline", baseline_path.display()))),
+    };
+    let days = if days.is_empty() { registry::SOLVED_DAYS.to_vec() } else { days };
+    let days_arg: Vec<String> = days.iter().map(i32::to_string).collect();
+    let scratch = std::env::temp_dir().join(format!("aoc_perf_{}.jsonl", process::id()));
+    let _ = fs::remove_file(&scratch);
+    for run in 1..=runs {
+        println!("Run {} of {}", run, runs);
+        let status = Process::new(std::env::current_exe()?)
+            .args(["-d", &days_arg.join(","), "--history"])
+            .arg(&scratch)
+            .stdout(process::Stdio::null())
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("run {} exited with {}", run, status)));
+        }
+    }
+    let records = history::load(&scratch)?;
+    fs::remove_file(&scratch)?;
+
+    let measured = perf::measure(&records, &days);
+    let comparisons = perf::compare(&baseline, &measured, tolerance);
+    perf::report(&comparisons, &mut io::stdout().lock())?;
+    if update_baseline {
+        let mut baseline = baseline;
+        baseline.days.extend(measured.into_iter().filter_map(|(day, timing)| Some((day, timing.ok()?))));
+        baseline.save(baseline_path)?;
+        println!("Updated {}", baseline_path.display());
+        return Ok(true);
+    }
+    let mut regressions: Vec<String> = comparisons.iter()
+        .filter(|comparison| comparison.verdict.is_regression())
+        .map(|comparison| comparison.day.to_string())
+        .collect();
+    regressions.dedup();
+    if regressions.is_empty() {
+        println!("No day is more than {:.0}% slower than the baseline", tolerance * 100.0);
+    } else {
+        println!("Regressed: day {}", regressions.join(", "));
+    }
+    Ok(regressions.is_empty())
+}
+
+fn display_day(day: i32, input_file_path: &Path, args: &Args) -> io::Result<()> {
+    let reader = BufReader::new(File::open(input_file_path)?);
+    match day {
+        2 => advent_of_code_2023::day_2::display(reader, &args.bag, &mut io::stdout().lock()),
+        7 => advent_of_code_2023::day_7::display(reader, &mut io::stdout().lock()),
+        9 => advent_of_code_2023::day_9::display(reader, &mut io::stdout().lock()),
+        _ => Ok(()),
+    }
+}
+
+fn export_day(day: i32, data: &str, dir: &Path) -> io::Result<()> {
+    let (extension, contents) = match day {
+        3 => ("svg", advent_of_code_2023::day_3::to_svg(data)?),
+        5 => ("svg", advent_of_code_2023::day_5::to_svg(data)?),
+        8 => ("dot", advent_of_code_2023::day_8::to_dot(data)),
+        _ => return Ok(()),
+    };
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("day_{}.{}", day, extension));
+    fs::write(&path, contents)?;
+    println!("Exported {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::helper::invalid_data;
+use crate::history::Record;
+
+/// The median times of each day when the baseline was last updated.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub days: BTreeMap<i32, DayTiming>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Timing {
+    pub median_secs: f64,
+    pub runs: usize,
+}
+
+impl Timing {
+    pub fn median(&self) -> Duration {
+        Duration::from_secs_f64(self.median_secs)
+    }
+}
+
+/// The median time of a whole day, and of each of its parts when the day times them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DayTiming {
+    pub total: Timing,
+    #[serde(default)]
+    pub parts: [Option<Timing>; 2],
+}
+
+impl DayTiming {
+    pub fn get(&self, measure: Measure) -> Option<Timing> {
+        match measure {
+            Measure::Total => Some(self.total),
+            Measure::Part(part) => self.parts[part - 1],
+        }
+    }
+}
+
+/// What a time covers: the whole day, parsing included, or one part counting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Measure {
+    Total,
+    Part(usize),
+}
+
+impl Measure {
+    const ALL: [Measure; 3] = [Measure::Total, Measure::Part(1), Measure::Part(2)];
+}
+
+impl fmt::Display for Measure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Measure::Total => write!(f, "total"),
+            Measure::Part(1) => write!(f, "part one"),
+            Measure::Part(_) => write!(f, "part two"),
+        }
+    }
+}
+
+impl Baseline {
+    /// The baseline stored at `path`, `None` if there is none yet.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).map(Some).map_err(|err| invalid_data(format!("{}: {}", path.display(), err))),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json + "\n")
+    }
+}
+
+/// The median of `times`, the mean of the middle two when there is an even number of them.
+pub fn median(times: &mut [Duration]) -> Option<Duration> {
+    times.sort_unstable();
+    let mid = times.len() / 2;
+    match times.len() {
+        0 => None,
+        len if len % 2 == 1 => Some(times[mid]),
+        _ => Some((times[mid - 1] + times[mid]) / 2),
+    }
+}
+
+/// The median times of each of `days` over the `records` of its runs. A day with a failed
+/// run, or with no runs at all, has an error instead, its timings would mean nothing.
+/// A part only has a median when every run timed it.
+pub fn measure(records: &[Record], days: &[i32]) -> BTreeMap<i32, Result<DayTiming, String>> {
+    let timing = |mut times: Vec<Duration>| Some(Timing { runs: times.len(), median_secs: median(&mut times)?.as_secs_f64() });
+    days.iter()
+        .map(|&day| {
+            let runs: Vec<&Record> = records.iter().filter(|record| record.day == day).collect();
+            if runs.is_empty() {
+                return (day, Err(format!("no runs recorded, is data/day_{}.txt missing?", day)));
+            }
+            if let Some(error) = runs.iter().find_map(|run| run.error.as_ref()) {
+                return (day, Err(error.clone()));
+            }
+            let total = timing(runs.iter().map(|run| run.elapsed()).collect()).unwrap();
+            let parts = [1, 2].map(|part| {
+                let times: Option<Vec<Duration>> = runs.iter().map(|run| run.part_elapsed(part)).collect();
+                timing(times?)
+            });
+            (day, Ok(DayTiming { total, parts }))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Faster,
+    Unchanged,
+    Slower,
+    /// Not in the baseline yet.
+    New,
+    Failed(String),
+}
+
+impl Verdict {
+    /// Whether the gate should fail on it.
+    pub fn is_regression(&self) -> bool {
+        matches!(self, Verdict::Slower | Verdict::Failed(_))
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Faster => write!(f, "faster"),
+            Verdict::Unchanged => write!(f, "ok"),
+            Verdict::Slower => write!(f, "SLOWER"),
+            Verdict::New => write!(f, "new"),
+            Verdict::Failed(error) => write!(f, "FAILED: {}", error),
+        }
+    }
+}
+
+/// How one median of a day compares with its baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub day: i32,
+    pub measure: Measure,
+    pub baseline: Option<Duration>,
+    pub median: Option<Duration>,
+    pub verdict: Verdict,
+}
+
+impl Comparison {
+    /// The change from the baseline as a fraction, `0.25` for a quarter slower.
+    pub fn change(&self) -> Option<f64> {
+        match (self.baseline, self.median) {
+            (Some(baseline), Some(median)) if !baseline.is_zero() => Some(median.as_secs_f64() / baseline.as_secs_f64() - 1.0),
+            _ => None,
+        }
+    }
+}
+
+/// Compares the total and each timed part of every measured day with `baseline`, so a part
+/// that got slower is caught even when the other got faster. A median is slower, or faster,
+/// when it moved by more than `tolerance`, a fraction of the baseline.
+pub fn compare(baseline: &Baseline, measured: &BTreeMap<i32, Result<DayTiming, String>>, tolerance: f64) -> Vec<Comparison> {
+    let mut comparisons = Vec::new();
+    for (&day, timing) in measured {
+        let baseline = baseline.days.get(&day);
+        let timing = match timing {
+            Ok(timing) => timing,
+            Err(error) => {
+                let baseline = baseline.map(|baseline| baseline.total.median());
+                comparisons.push(Comparison { day, measure: Measure::Total, baseline, median: None, verdict: Verdict::Failed(error.clone()) });
+                continue;
+            }
+        };
+        for measure in Measure::ALL {
+            let Some(median) = timing.get(measure).map(|timing| timing.median()) else {
+                continue;
+            };
+            let baseline = baseline.and_then(|baseline| baseline.get(measure)).map(|timing| timing.median());
+            let mut comparison = Comparison { day, measure, baseline, median: Some(median), verdict: Verdict::New };
+            comparison.verdict = match comparison.change() {
+                None if baseline.is_none() => Verdict::New,
+                Some(change) if change > tolerance => Verdict::Slower,
+                Some(change) if change < -tolerance => Verdict::Faster,
+                _ => Verdict::Unchanged,
+            };
+            comparisons.push(comparison);
+        }
+    }
+    comparisons
+}
+
+/// Prints one row per median compared: its baseline, its value now, the change and the verdict.
+pub fn report<W: Write>(comparisons: &[Comparison], out: &mut W) -> io::Result<()> {
+    let time = |time: Option<Duration>| time.map_or_else(|| String::from("-"), |time| format!("{:.3?}", time));
+    writeln!(out, "{:>3}  {:<8}  {:>12}  {:>12}  {:>8}  status", "day", "", "baseline", "median", "change")?;
+    for comparison in comparisons {
+        let change = comparison.change().map_or_else(String::new, |change| format!("{:+.1}%", change * 100.0));
+        writeln!(out, "{:>3}  {:<8}  {:>12}  {:>12}  {:>8}  {}", comparison.day, comparison.measure.to_string(),
+                 time(comparison.baseline), time(comparison.median), change, comparison.verdict)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::answers::Answers;
+
+    use super::*;
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn run(day: i32, elapsed_secs: f64, part_secs: [Option<f64>; 2], error: Option<&str>) -> Record {
+        Record {
+            timestamp: 0,
+            day,
+            input: format!("data/day_{}.txt", day),
+            input_hash: String::new(),
+            commit: None,
+            options: Vec::new(),
+            elapsed_secs,
+            part_secs,
+            answers: Answers::default(),
+            error: error.map(String::from),
+        }
+    }
+
+    fn timing(median_secs: f64) -> Timing {
+        Timing { median_secs, runs: 3 }
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut []), None);
+        assert_eq!(median(&mut [millis(3), millis(1), millis(2)]), Some(millis(2)));
+        assert_eq!(median(&mut [millis(4), millis(1), millis(2), millis(100)]), Some(millis(3)));
+    }
+
+    #[test]
+    fn test_measure() {
+        let records = [
+            run(5, 0.010, [Some(0.001), Some(0.008)], None),
+            run(5, 0.012, [Some(0.002), Some(0.009)], None),
+            run(5, 0.500, [Some(0.003), Some(0.490)], None),
+            run(1, 0.010, [None, None], None),
+            run(9, 0.001, [None, None], None),
+            run(9, 1.000, [None, None], Some("timed out")),
+        ];
+        let measured = measure(&records, &[1, 5, 9, 12]);
+        assert_eq!(measured[&5], Ok(DayTiming { total: timing(0.012), parts: [Some(timing(0.002)), Some(timing(0.009))] }));
+        assert_eq!(measured[&1], Ok(DayTiming { total: Timing { median_secs: 0.010, runs: 1 }, parts: [None, None] }));
+        assert_eq!(measured[&9], Err(String::from("timed out")));
+        assert_eq!(measured[&12], Err(String::from("no runs recorded, is data/day_12.txt missing?")));
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut measured = BTreeMap::new();
+        // Part one got much slower, hidden in the total by part two getting faster.
+        measured.insert(5, Ok(DayTiming { total: timing(0.010), parts: [Some(timing(0.004)), Some(timing(0.005))] }));
+        measured.insert(6, Ok(DayTiming { total: timing(0.020), parts: [None, None] }));
+        measured.insert(7, Ok(DayTiming { total: timing(0.030), parts: [None, None] }));
+        measured.insert(9, Err(String::from("timed out")));
+
+        let mut baseline = Baseline::default();
+        baseline.days.insert(5, DayTiming { total: timing(0.010), parts: [Some(timing(0.001)), Some(timing(0.008))] });
+        baseline.days.insert(6, DayTiming { total: timing(0.010), parts: [None, None] });
+        baseline.days.insert(9, DayTiming { total: timing(0.001), parts: [None, None] });
+        let verdicts: Vec<(i32, Measure, Verdict)> = compare(&baseline, &measured, 0.1).into_iter()
+            .map(|comparison| (comparison.day, comparison.measure, comparison.verdict))
+            .collect();
+        assert_eq!(verdicts, vec![
+            (5, Measure::Total, Verdict::Unchanged),
+            (5, Measure::Part(1), Verdict::Slower),
+            (5, Measure::Part(2), Verdict::Faster),
+            (6, Measure::Total, Verdict::Slower),
+            (7, Measure::Total, Verdict::New),
+            (9, Measure::Total, Verdict::Failed(String::from("timed out"))),
+        ]);
+    }
+
+    #[test]
+    fn test_report() {
+        let comparison = Comparison {
+            day: 6,
+            measure: Measure::Part(2),
+            baseline: Some(millis(10)),
+            median: Some(millis(15)),
+            verdict: Verdict::Slower,
+        };
+        let mut out = Vec::new();
+        report(&[comparison], &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.lines().nth(1).unwrap().ends_with("part two      10.000ms      15.000ms    +50.0%  SLOWER"), "{}", out);
+    }
+}
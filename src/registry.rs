@@ -0,0 +1,47 @@
+use std::io;
+
+use crate::budget::Budget;
+use crate::day_6;
+use crate::explain::Trace;
+
+/// One way of solving a day, returning both answers instead of printing them.
+#[derive(Debug, Clone, Copy)]
+pub struct Implementation {
+    pub name: &'static str,
+    pub answers: fn(&str, &mut Trace, &Budget) -> io::Result<(u64, u64)>,
+}
+
+/// The days that have a solution, the others print that they are not solved yet.
+pub const SOLVED_DAYS: [i32; 9] = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+
+const DAY_6: [Implementation; 2] = [
+    Implementation { name: "naive", answers: day_6::answers_naive },
+    Implementation { name: "fast", answers: day_6::answers_fast },
+];
+
+/// Every registered implementation of `day`, naive first. Days with a single
+/// implementation are solved directly and have none registered.
+pub fn implementations(day: i32) -> &'static [Implementation] {
+    match day {
+        6 => &DAY_6,
+        _ => &[],
+    }
+}
+
+/// The registered implementation of `day` called `name`, if there is one.
+pub fn find(day: i32, name: &str) -> Option<&'static Implementation> {
+    implementations(day).iter().find(|implementation| implementation.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(implementations(6).len(), 2);
+        assert_eq!(find(6, "fast").map(|implementation| implementation.name), Some("fast"));
+        assert!(find(6, "slow").is_none());
+        assert!(implementations(1).is_empty());
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use serde::Deserialize;
+
+use crate::answers::{self, Answers, Status};
+use crate::explain::{self, TraceEntry};
+use crate::registry;
+use crate::watch;
+
+const COLUMNS: usize = 5;
+
+/// What one run of the runner reported, as read back from its `--explain json` line.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct RunReport {
+    pub answers: Answers,
+    pub trace: Vec<TraceEntry>,
+    /// Why the day failed or timed out, if it printed a reason.
+    #[serde(skip)]
+    pub error: Option<String>,
+}
+
+impl RunReport {
+    /// Reads the report out of everything the runner printed for one day.
+    pub fn parse(stdout: &str) -> Option<Self> {
+        let json = stdout.lines().rev().find(|line| line.starts_with('{'))?;
+        let mut report: RunReport = serde_json::from_str(json).ok()?;
+        report.error = stdout.lines()
+            .find(|line| line.starts_with("Failed to solve day") || line.ends_with("timed out"))
+            .map(String::from);
+        Some(report)
+    }
+
+    /// The trace recorded for part `part`, or all of it when the day does not split it by part.
+    pub fn part_trace(&self, part: usize) -> &[TraceEntry] {
+        let title = if part == 1 { "part one" } else { "part two" };
+        match self.trace.iter().find(|entry| entry.message == title) {
+            Some(section) => std::slice::from_ref(section),
+            None => &self.trace,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Run {
+    report: RunReport,
+    elapsed: Duration,
+}
+
+/// Everything the TUI shows, changed only by `handle_key` and `run_selected`.
+#[derive(Debug)]
+pub struct App {
+    pub selected_day: i32,
+    /// Index into `inputs` of the selected input, the real input being the last.
+    pub input_idx: usize,
+    /// The inputs of the selected day, listed when it is selected rather than on every frame.
+    inputs: Vec<PathBuf>,
+    pub part: usize,
+    pub showing_trace: bool,
+    pub trace_scroll: u16,
+    pub quit: bool,
+    expected: BTreeMap<String, Answers>,
+    runs: HashMap<PathBuf, Run>,
+}
+
+/// What a key asks the app to do besides changing its own state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Run,
+}
+
+impl App {
+    pub fn new(expected: BTreeMap<String, Answers>) -> Self {
+        let inputs = watch::inputs(1);
+        App {
+            selected_day: 1,
+            input_idx: inputs.len() - 1,
+            inputs,
+            part: 1,
+            showing_trace: false,
+            trace_scroll: 0,
+            quit: false,
+            expected,
+            runs: HashMap::new(),
+        }
+    }
+
+    /// The selected input of the selected day, the real one unless an example was picked.
+    pub fn input(&self) -> PathBuf {
+        self.inputs[self.input_idx].clone()
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> Action {
+        let day = self.selected_day;
+        let move_to = |day: i32| if (1..=25).contains(&day) { day } else { self.selected_day };
+        match key {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc if self.showing_trace => self.showing_trace = false,
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Up if self.showing_trace => self.trace_scroll = self.trace_scroll.saturating_sub(1),
+            KeyCode::Down if self.showing_trace => self.trace_scroll = self.trace_scroll.saturating_add(1),
+            KeyCode::Left | KeyCode::Char('h') => self.select(move_to(day - 1)),
+            KeyCode::Right | KeyCode::Char('l') => self.select(move_to(day + 1)),
+            KeyCode::Up | KeyCode::Char('k') => self.select(move_to(day - COLUMNS as i32)),
+            KeyCode::Down | KeyCode::Char('j') => self.select(move_to(day + COLUMNS as i32)),
+            KeyCode::Char('e') => self.input_idx = (self.input_idx + 1) % self.inputs.len(),
+            KeyCode::Char('1') => self.part = 1,
+            KeyCode::Char('2') => self.part = 2,
+            KeyCode::Tab => self.part = 3 - self.part,
+            KeyCode::Char('t') => {
+                self.showing_trace = !self.showing_trace;
+                self.trace_scroll = 0;
+            }
+            KeyCode::Enter | KeyCode::Char('r') if registry::SOLVED_DAYS.contains(&day) => return Action::Run,
+            _ => (),
+        }
+        Action::None
+    }
+
+    fn select(&mut self, day: i32) {
+        if day != self.selected_day {
+            self.selected_day = day;
+            self.inputs = watch::inputs(day);
+            self.input_idx = self.inputs.len() - 1;
+            self.showing_trace = false;
+        }
+    }
+
+    /// Runs the selected day on the selected input in a child process, so its printing
+    /// stays off the screen, and keeps what it reports.
+    pub fn run_selected(&mut self) -> io::Result<()> {
+        let input = self.input();
+        let start = Instant::now();
+        let output = Command::new(env::current_exe()?)
+            .args(["-d", &self.selected_day.to_string(), "--explain", "json", "--input"])
+            .arg(&input)
+            .output()?;
+        let elapsed = start.elapsed();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let report = RunReport::parse(&stdout).unwrap_or_else(|| RunReport {
+            error: Some(format!("no report in the output of {}", input.display())),
+            ..RunReport::default()
+        });
+        self.runs.insert(input, Run { report, elapsed });
+        Ok(())
+    }
+
+    /// How part `part` of `day`'s last run on `input` compares with the stored answer,
+    /// `None` if it was never run.
+    fn status(&self, input: &Path, part: usize) -> Option<Status> {
+        let run = self.runs.get(input)?;
+        let expected = self.expected.get(&input.to_string_lossy().into_owned());
+        Some(Status::of(run.report.answers.part(part), expected.and_then(|expected| expected.part(part))))
+    }
+}
+
+/// Opens the calendar of every day and keeps it on screen until the user quits.
+///
+/// Must be started from the crate root, it reads the inputs the way the runner does.
+pub fn run() -> io::Result<()> {
+    let mut app = App::new(answers::load_expected(Path::new("data/answers.json"))?);
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    while !app.quit {
+        terminal.draw(|frame| draw(frame, app, false))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if app.handle_key(key.code) == Action::Run {
+            terminal.draw(|frame| draw(frame, app, true))?;
+            app.run_selected()?;
+        }
+    }
+    Ok(())
+}
+
+fn status_span(status: Option<Status>) -> Span<'static> {
+    match status {
+        None => Span::styled("·", Style::default().fg(Color::DarkGray)),
+        Some(Status::Correct) => Span::styled("*", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Some(Status::Wrong) => Span::styled("x", Style::default().fg(Color::Red)),
+        Some(Status::Unknown) => Span::styled("?", Style::default().fg(Color::Cyan)),
+    }
+}
+
+fn draw(frame: &mut Frame, app: &App, running: bool) {
+    let [calendar, details, help] = Layout::vertical([
+        Constraint::Length(3 * 5 + 2),
+        Constraint::Min(6),
+        Constraint::Length(1),
+    ]).areas(frame.area());
+
+    draw_calendar(frame, app, calendar);
+    if app.showing_trace {
+        draw_trace(frame, app, details);
+    } else {
+        draw_details(frame, app, details, running);
+    }
+    let keys = "arrows move  enter run  e input  1/2 part  t trace  q quit";
+    frame.render_widget(Paragraph::new(keys).style(Style::default().fg(Color::DarkGray)), help);
+}
+
+fn draw_calendar(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::bordered().title(" Advent of Code 2023 ");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let rows = Layout::vertical([Constraint::Length(3); 5]).split(inner);
+    for (row_idx, row) in rows.iter().enumerate() {
+        let cells = Layout::horizontal([Constraint::Ratio(1, COLUMNS as u32); COLUMNS]).split(*row);
+        for (col_idx, cell) in cells.iter().enumerate() {
+            let day = (row_idx * COLUMNS + col_idx + 1) as i32;
+            let solved = registry::SOLVED_DAYS.contains(&day);
+            let real_input = watch::real_input(day);
+            let mut line = vec![Span::raw(format!("Day {:>2} ", day))];
+            if solved {
+                line.extend((1..=2).map(|part| status_span(app.status(&real_input, part))));
+            } else {
+                line.push(Span::styled("--", Style::default().fg(Color::DarkGray)));
+            }
+            let mut style = Style::default();
+            if !solved {
+                style = style.fg(Color::DarkGray);
+            }
+            if day == app.selected_day {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            frame.render_widget(Paragraph::new(Line::from(line)).style(style).block(Block::bordered()), *cell);
+        }
+    }
+}
+
+fn draw_details(frame: &mut Frame, app: &App, area: Rect, running: bool) {
+    let input = app.input();
+    let key = input.to_string_lossy().into_owned();
+    let mut lines = vec![Line::from(format!("Input: {}", input.display()))];
+    if !registry::SOLVED_DAYS.contains(&app.selected_day) {
+        lines.push(Line::from("Not solved yet"));
+    } else if running {
+        lines.push(Line::from("Running..."));
+    } else if let Some(run) = app.runs.get(&input) {
+        for part in 1..=2 {
+            let answer = run.report.answers.part(part).unwrap_or("-");
+            let expected = app.expected.get(&key).and_then(|expected| expected.part(part));
+            let marker = if part == app.part { "> " } else { "  " };
+            let mut line = vec![Span::raw(format!("{}Part {}: {} ", marker, part, answer)), status_span(app.status(&input, part))];
+            if let (Status::Wrong, Some(expected)) = (Status::of(run.report.answers.part(part), expected), expected) {
+                line.push(Span::raw(format!(" expected {}", expected)));
+            }
+            lines.push(Line::from(line));
+        }
+        lines.push(Line::from(format!("Last run took {:.3?}", run.elapsed)));
+        if let Some(error) = &run.report.error {
+            lines.push(Line::styled(error.clone(), Style::default().fg(Color::Red)));
+        }
+    } else {
+        lines.push(Line::from("Not run yet, press enter"));
+    }
+    let title = format!(" Day {} ", app.selected_day);
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title)), area);
+}
+
+fn draw_trace(frame: &mut Frame, app: &App, area: Rect) {
+    let text = match app.runs.get(&app.input()) {
+        Some(run) if !run.report.trace.is_empty() => explain::render_entries_text(run.report.part_trace(app.part)),
+        Some(_) => String::from("The run recorded no trace"),
+        None => String::from("Not run yet, press enter"),
+    };
+    let title = format!(" Day {} part {} trace ", app.selected_day, app.part);
+    let paragraph = Paragraph::new(text).scroll((app.trace_scroll, 0)).block(Block::bordered().title(title));
+    frame.render_widget(paragraph, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message: &str, children: Vec<TraceEntry>) -> TraceEntry {
+        TraceEntry { message: String::from(message), children }
+    }
+
+    #[test]
+    fn test_parse_report() {
+        let stdout = "Day 8\nSolving day 8 problems...\nStarting node AAA not found.\n\
+                      {\"day\":8,\"answers\":{\"part_one\":null,\"part_two\":\"6\"},\"trace\":[{\"message\":\"part one\"},{\"message\":\"part two\",\"children\":[{\"message\":\"a\"}]}]}\n\n";
+        let report = RunReport::parse(stdout).unwrap();
+        assert_eq!(report.answers, Answers { part_one: None, part_two: Some(String::from("6")) });
+        assert_eq!(report.part_trace(2), &[entry("part two", vec![entry("a", Vec::new())])]);
+        assert_eq!(report.error, None);
+
+        let report = RunReport::parse("Day 6\nDay 6 timed out\n{\"day\":6,\"answers\":{\"part_one\":null,\"part_two\":null},\"trace\":[]}").unwrap();
+        assert_eq!(report.error.as_deref(), Some("Day 6 timed out"));
+        assert_eq!(RunReport::parse("Day 6\n"), None);
+    }
+
+    #[test]
+    fn test_part_trace_without_parts() {
+        let report = RunReport { trace: vec![entry("line 1", Vec::new())], ..RunReport::default() };
+        assert_eq!(report.part_trace(1), report.trace.as_slice());
+    }
+
+    #[test]
+    fn test_keys() {
+        let mut app = App::new(BTreeMap::new());
+        app.handle_key(KeyCode::Down);
+        app.handle_key(KeyCode::Right);
+        assert_eq!(app.selected_day, 7);
+        app.handle_key(KeyCode::Up);
+        app.handle_key(KeyCode::Up);
+        assert_eq!(app.selected_day, 2);
+        assert_eq!(app.input(), PathBuf::from("data/day_2.txt"));
+        app.handle_key(KeyCode::Char('e'));
+        assert_eq!(app.input(), PathBuf::from("data/examples/day_2/example.txt"));
+        app.handle_key(KeyCode::Char('e'));
+        assert_eq!(app.input(), PathBuf::from("data/day_2.txt"));
+
+        assert_eq!(app.handle_key(KeyCode::Enter), Action::Run);
+        app.handle_key(KeyCode::Tab);
+        assert_eq!(app.part, 2);
+        app.handle_key(KeyCode::Char('t'));
+        app.handle_key(KeyCode::Down);
+        assert_eq!((app.showing_trace, app.trace_scroll, app.selected_day), (true, 1, 2));
+        app.handle_key(KeyCode::Esc);
+        assert!(!app.showing_trace && !app.quit);
+
+        app.select(25);
+        assert_eq!(app.handle_key(KeyCode::Enter), Action::None);
+        app.handle_key(KeyCode::Right);
+        assert_eq!(app.selected_day, 25);
+        app.handle_key(KeyCode::Char('q'));
+        assert!(app.quit);
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+/// How long to wait for more changes once one arrives, editors write a file in several steps.
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// Whether a change to `path`, relative to the crate root, can change `day`'s answers:
+/// its source, its input, or one of its examples.
+pub fn is_watched(day: i32, path: &Path) -> bool {
+    path == Path::new(&format!("src/day_{}.rs", day))
+        || path.starts_with(format!("src/day_{}", day))
+        || path == Path::new(&format!("data/day_{}.txt", day))
+        || path.starts_with(format!("data/examples/day_{}", day))
+}
+
+/// The example inputs of `day` in name order, then its real input.
+pub fn inputs(day: i32) -> Vec<PathBuf> {
+    let mut inputs: Vec<PathBuf> = fs::read_dir(format!("data/examples/day_{}", day)).into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    inputs.sort();
+    inputs.push(real_input(day));
+    inputs
+}
+
+/// The puzzle input of `day`, where the runner reads it from.
+pub fn real_input(day: i32) -> PathBuf {
+    PathBuf::from(format!("data/day_{}.txt", day))
+}
+
+/// The lines that changed between two runs, `-` for the old one and `+` for the new one.
+/// Runs print the same lines in the same order, so lines are compared by position.
+pub fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let (old, new): (Vec<&str>, Vec<&str>) = (old.lines().collect(), new.lines().collect());
+    let mut diff = Vec::new();
+    for idx in 0..old.len().max(new.len()) {
+        let (old_line, new_line) = (old.get(idx), new.get(idx));
+        if old_line != new_line {
+            diff.extend(old_line.map(|line| format!("- {}", line)));
+            diff.extend(new_line.map(|line| format!("+ {}", line)));
+        }
+    }
+    diff
+}
+
+/// Rebuilds and reruns `day` every time one of its files changes, printing how the
+/// output of each input differs from the last run. Runs until the watcher stops.
+///
+/// Must be started from the crate root, it finds the files the way the runner does.
+pub fn watch(day: i32) -> io::Result<()> {
+    let root = env::current_dir()?.canonicalize()?;
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(io::Error::other)?;
+    // Directories rather than files, editors often save by replacing the file.
+    for dir in ["src", "data"] {
+        watcher.watch(&root.join(dir), RecursiveMode::Recursive).map_err(io::Error::other)?;
+    }
+
+    let mut previous = HashMap::new();
+    loop {
+        run_once(day, &mut previous)?;
+        println!("Watching day {} for changes...", day);
+        loop {
+            let event = match receiver.recv() {
+                Ok(event) => event.map_err(io::Error::other)?,
+                Err(_) => return Ok(()),
+            };
+            // Running the day opens its files too, only writes count.
+            let is_write = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_));
+            let touches_day = event.paths.iter()
+                .any(|path| path.strip_prefix(&root).is_ok_and(|path| is_watched(day, path)));
+            if is_write && touches_day {
+                break;
+            }
+        }
+        while receiver.recv_timeout(SETTLE_TIME).is_ok() {}
+    }
+}
+
+/// Builds the crate, then runs `day` on every input, diffing each output with `previous`.
+/// A build that fails is reported and leaves `previous` as it was.
+fn run_once(day: i32, previous: &mut HashMap<PathBuf, String>) -> io::Result<()> {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| String::from("cargo"));
+    if !Command::new(&cargo).args(["build", "--quiet"]).status()?.success() {
+        println!("Build failed, waiting for the next change");
+        return Ok(());
+    }
+    for input in inputs(day) {
+        if !input.exists() {
+            continue;
+        }
+        let output = Command::new(&cargo)
+            .args(["run", "--quiet", "--", "-d", &day.to_string(), "--input"])
+            .arg(&input)
+            .output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        println!("== {} ==", input.display());
+        print!("{}", stdout);
+        match previous.get(&input) {
+            Some(old) if *old == stdout => println!("(unchanged)"),
+            Some(old) => {
+                println!("Changed since the last run:");
+                for line in diff_lines(old, &stdout) {
+                    println!("{}", line);
+                }
+            }
+            None => (),
+        }
+        previous.insert(input, stdout);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_watched() {
+        assert!(is_watched(7, Path::new("src/day_7.rs")));
+        assert!(is_watched(7, Path::new("src/day_7/stats.rs")));
+        assert!(is_watched(7, Path::new("data/day_7.txt")));
+        assert!(is_watched(8, Path::new("data/examples/day_8/part_two.txt")));
+        assert!(!is_watched(1, Path::new("src/day_17.rs")));
+        assert!(!is_watched(1, Path::new("data/day_17.txt")));
+        assert!(!is_watched(1, Path::new("src/helper.rs")));
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let old = "Day 6\nanswer: 288\nanswer: 71503\n";
+        assert!(diff_lines(old, old).is_empty());
+        assert_eq!(diff_lines(old, "Day 6\nanswer: 289\nanswer: 71503\n"), vec!["- answer: 288", "+ answer: 289"]);
+        assert_eq!(diff_lines(old, "Day 6\n"), vec!["- answer: 288", "- answer: 71503"]);
+    }
+}
@@ -0,0 +1,89 @@
+//! Runs the compiled binary on every example in `data/examples` and compares what it
+//! prints with the snapshots stored in `tests/snapshots`.
+//!
+//! After changing the output on purpose, rewrite the snapshots and review the diff:
+//!
+//! ```sh
+//! INSTA_UPDATE=always cargo test --test snapshots
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Every example input as `(day, name, path)`, the name being the file stem.
+fn examples() -> Vec<(u32, String, PathBuf)> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("data").join("examples");
+    let mut examples = Vec::new();
+    for day in 1..=25 {
+        let Ok(entries) = fs::read_dir(root.join(format!("day_{}", day))) else {
+            continue;
+        };
+        for entry in entries {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            examples.push((day, name, path));
+        }
+    }
+    examples.sort();
+    examples
+}
+
+fn run(day: u32, input: &Path, extra_args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_advent_of_code_2023"))
+        .args(["-d", &day.to_string(), "--input"])
+        .arg(input)
+        .arg("--no-history")
+        .args(extra_args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "day {} on {} exited with {}", day, input.display(), output.status);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_text_output() {
+    for (day, name, path) in examples() {
+        let stdout = run(day, &path, &["--display"]);
+        // A snapshot of a failure would only check that the day keeps failing.
+        assert!(!stdout.contains("Failed to solve"), "day {} failed on {}:\n{}", day, path.display(), stdout);
+        insta::assert_snapshot!(format!("day_{}_{}", day, name), stdout);
+    }
+}
+
+#[test]
+fn test_json_trace() {
+    for (day, name, path) in examples() {
+        let stdout = run(day, &path, &["--explain", "json"]);
+        let json = stdout.lines().rev().find(|line| line.starts_with('{'))
+            .unwrap_or_else(|| panic!("day {} on {} printed no trace", day, path.display()));
+        let trace: serde_json::Value = serde_json::from_str(json).unwrap();
+        insta::assert_snapshot!(format!("day_{}_{}_json", day, name), serde_json::to_string_pretty(&trace).unwrap());
+    }
+}
+
+#[test]
+fn test_unsolved_day() {
+    let stdout = run(10, &Path::new(env!("CARGO_MANIFEST_DIR")).join("data/examples/day_6/example.txt"), &[]);
+    assert_eq!(stdout, "Day 10\nDay 10 is not solved yet\n\n");
+}
+
+#[test]
+fn test_example_answers() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let expected = advent_of_code_2023::answers::load_expected(&root.join("data/answers.json")).unwrap();
+    for (day, name, path) in examples() {
+        let key = path.strip_prefix(root).unwrap().to_string_lossy().into_owned();
+        let stdout = run(day, &path, &["--explain", "json"]);
+        let json = stdout.lines().rev().find(|line| line.starts_with('{')).unwrap();
+        let report: serde_json::Value = serde_json::from_str(json).unwrap();
+        let answers: advent_of_code_2023::answers::Answers = serde_json::from_value(report["answers"].clone()).unwrap();
+        let expected = expected.get(&key).unwrap_or_else(|| panic!("no stored answers for day {} {}", day, name));
+        for part in 1..=2 {
+            if let Some(expected) = expected.part(part) {
+                assert_eq!(answers.part(part), Some(expected), "day {} {} part {}", day, name, part);
+            }
+        }
+    }
+}